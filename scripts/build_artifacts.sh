@@ -30,6 +30,14 @@ ProjName="$(
 rm -r target/build_artifacts || true
 mkdir target/build_artifacts
 
+#
+# Convert levels to the binary format loaded by release builds
+#
+
+for Level in assets/*.klodlvl ; do
+	cargo run --features editor --bin klod-convert -- "$Level" "${Level%.klodlvl}.klodbin"
+done
+
 #
 # Build
 #
@@ -78,6 +78,14 @@ else
 	echo "install it, it has been known to divide by two wasm files size"
 fi
 
+#
+# Convert levels to the binary format loaded by release builds
+#
+
+for Level in assets/*.klodlvl ; do
+	cargo run --features editor --bin klod-convert -- "$Level" "${Level%.klodlvl}.klodbin"
+done
+
 #
 # Copy files
 #
@@ -163,7 +163,7 @@ fn play_music(
 
 #[cfg_attr(feature = "debug", derive(Inspectable))]
 #[cfg_attr(feature = "editor", derive(serde::Serialize))]
-#[derive(Deserialize, Debug, Clone, Default, Copy)]
+#[derive(Deserialize, Debug, Clone, Default, Copy, PartialEq)]
 pub(crate) enum Pitch {
     High,
     #[default]
@@ -172,7 +172,7 @@ pub(crate) enum Pitch {
 }
 #[cfg_attr(feature = "debug", derive(Inspectable))]
 #[cfg_attr(feature = "editor", derive(serde::Serialize))]
-#[derive(Deserialize, Debug, Clone, Default, Copy)]
+#[derive(Deserialize, Debug, Clone, Default, Copy, PartialEq)]
 pub(crate) enum ImpactSound {
     Explosion,
     Bell,
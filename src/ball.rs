@@ -1,31 +1,50 @@
 pub(crate) mod anim;
+pub(crate) mod slurp;
+pub(crate) mod tuning;
+
+use std::{cmp::Reverse, f32::consts::PI};
 
 use bevy::{
     ecs::system::EntityCommands,
     math::Vec3Swizzles,
     prelude::{Plugin as BevyPlugin, *},
+    utils::FloatOrd,
 };
 use bevy_debug_text_overlay::screen_print;
 #[cfg(feature = "debug")]
 use bevy_inspector_egui::{Inspectable, RegisterInspectable};
 use bevy_rapier3d::prelude::*;
 
-use self::anim::KlodVisualElem;
+use self::{
+    anim::{KlodBallVisual, KlodVisualElem},
+    slurp::{Slurp, SlurpRejected, SlurpRules},
+    tuning::KlodTuning,
+};
 #[cfg(not(feature = "editor"))]
 use crate::scene::reset_scene;
 use crate::{
-    cam::OrbitCamera, collision_groups as groups, powers::Power, prefabs::AggloBundle,
-    state::GameState, system_helper::EasySystemSetCtor, UsesGamepad,
+    animate::Animate, audio::AudioRequestSystem, cam::OrbitCamera, collision_groups as groups,
+    powers::Power, prefabs::AggloBundle, rules::LevelEvent, state::GameState,
+    system_helper::EasySystemSetCtor, UsesGamepad,
 };
 
-const BASE_INPUT_IMPULSE: f32 = 1.0;
-const INPUT_WEIGHT_COMP: f32 = 0.5;
-const KLOD_INITIAL_WEIGHT: f32 = 4.2;
 const KLOD_INITIAL_RADIUS: f32 = 1.0;
-pub(crate) const MAX_KLOD_SPEED: f32 = 28.0;
+/// How fast the klod radius grows toward the size of its volume, in units per second.
+const KLOD_GROWTH_SPEED: f32 = 0.5;
+/// Contact force above which impacts knock items off the klod.
+const KNOCK_OFF_FORCE: f32 = 4000.0;
+/// How many items a single impact can knock off at most.
+const MAX_KNOCKED_OFF: usize = 3;
+/// Seconds between two knock offs, so that scraping a wall doesn't empty the klod.
+const KNOCK_OFF_COOLDOWN: f64 = 1.0;
+/// Seconds before a knocked off item can be agglomerated again.
+const KNOCKED_OFF_SECONDS: f32 = 1.5;
+/// Speed at which knocked off items are thrown away from the klod.
+const KNOCK_OFF_SPEED: f32 = 6.0;
 
 #[derive(SystemLabel)]
 pub(crate) enum BallSystems {
+    Input,
     FreeFallUpdate,
     DestroyKlod,
     ResetKlod,
@@ -37,30 +56,33 @@ pub(crate) struct KlodCamera;
 #[cfg_attr(feature = "debug", derive(Inspectable))]
 #[derive(Component)]
 pub(crate) struct Klod {
+    /// Weight of the klod ball alone, see [`KlodTuning::initial_weight`].
+    initial_weight: f32,
     weight: f32,
+    /// Volume of the klod ball and everything it absorbed.
+    volume: f32,
+    /// Current radius of the klod ball, growing toward the radius of `volume`.
+    radius: f32,
 }
 impl Klod {
-    fn within_radius(&self, distance: f32) -> bool {
-        let max_distance = self.weight / KLOD_INITIAL_WEIGHT;
-        let can_slurp = distance < max_distance;
-        let color = if can_slurp { Color::GREEN } else { Color::RED };
-        screen_print!(col: color, "slurp dist: {distance:.3} <? {max_distance:.3}");
-        can_slurp
+    fn new(initial_weight: f32) -> Self {
+        Klod {
+            initial_weight,
+            weight: initial_weight,
+            volume: 4.0 / 3.0 * PI * KLOD_INITIAL_RADIUS.powi(3),
+            radius: KLOD_INITIAL_RADIUS,
+        }
     }
-    fn can_slurp(&self, weight: f32, velocity: Vec3) -> bool {
-        let speed_bonus = (velocity.length() * 1.2 / MAX_KLOD_SPEED).max(0.5);
-        let weight_limit = self.weight / 10.0;
-        let can_slurp = weight < speed_bonus * weight_limit;
-        let color = if can_slurp { Color::GREEN } else { Color::RED };
-        screen_print!(
-            col: color,
-            "slurp: {weight:.3} <? {speed_bonus:.3} * {weight_limit:.3}"
-        );
-        can_slurp
+    /// Radius of a ball holding the klod's volume.
+    fn target_radius(&self) -> f32 {
+        (self.volume * 3.0 / (4.0 * PI)).cbrt()
     }
-
     pub(crate) fn weight(&self) -> f32 {
-        (self.weight - KLOD_INITIAL_WEIGHT) * 10.0
+        (self.weight - self.initial_weight) * 10.0
+    }
+    /// Mass of the klod rigid body, including the ball itself.
+    pub(crate) fn mass(&self) -> f32 {
+        self.weight
     }
 }
 #[derive(Component)]
@@ -68,7 +90,7 @@ pub(crate) struct KlodBall;
 
 #[derive(Component)]
 pub(crate) struct KlodElem {
-    klod: Entity,
+    pub(crate) klod: Entity,
     pub(crate) scene: Option<Entity>,
 }
 
@@ -105,13 +127,13 @@ fn spawn_klod_elem<'w, 's, 'a>(
         power,
     ))
 }
-fn spawn_ball(cmds: &mut ChildBuilder) {
+fn spawn_ball(cmds: &mut ChildBuilder, weight: f32) {
     let klod = cmds.parent_entity();
     let mut ball = spawn_klod_elem(
         cmds,
         "Klod ball".to_owned(),
         KlodElem { klod, scene: None },
-        KLOD_INITIAL_WEIGHT,
+        weight,
         Collider::ball(KLOD_INITIAL_RADIUS),
         default(),
         Friction {
@@ -134,13 +156,14 @@ fn reset_klod(
     cam: Query<Entity, With<KlodCamera>>,
     asset_server: Res<AssetServer>,
     spawn_point: Res<KlodSpawnTransform>,
+    tuning: Res<KlodTuning>,
     other_klod_elems: Query<Entity, Or<(With<KlodElem>, With<KlodVisualElem>)>>,
 ) -> Option<()> {
     if klod_exists.is_empty() {
-        spawn_klod(cmds, klod_exists, cam, asset_server, spawn_point)
+        spawn_klod(cmds, klod_exists, cam, asset_server, spawn_point, tuning)
     } else {
         let (klod, mut klod_value, mut klod_velocity) = klod_entity.get_single_mut().ok()?;
-        klod_value.weight = KLOD_INITIAL_WEIGHT;
+        *klod_value = Klod::new(tuning.initial_weight);
         *klod_velocity = default();
         other_klod_elems.for_each(|entity| {
             cmds.entity(entity).despawn_recursive();
@@ -148,7 +171,7 @@ fn reset_klod(
         cmds.entity(klod)
             .insert(spawn_point.0)
             .add_children(|cmds| {
-                spawn_ball(cmds);
+                spawn_ball(cmds, tuning.initial_weight);
                 anim::spawn_klod_visuals(cmds, &asset_server);
             });
     }
@@ -161,6 +184,7 @@ fn spawn_klod(
     cam: Query<Entity, With<KlodCamera>>,
     asset_server: Res<AssetServer>,
     spawn_point: Res<KlodSpawnTransform>,
+    tuning: Res<KlodTuning>,
 ) {
     if !klod_exists.is_empty() {
         return;
@@ -171,7 +195,7 @@ fn spawn_klod(
     };
     let klod = cmds
         .spawn_bundle((
-            Klod { weight: KLOD_INITIAL_WEIGHT },
+            Klod::new(tuning.initial_weight),
             FreeFall(true),
             RigidBody::Dynamic,
             ExternalImpulse::default(),
@@ -181,7 +205,7 @@ fn spawn_klod(
         ))
         .insert_bundle(SpatialBundle::from_transform(spawn_point.0))
         .with_children(|cmds| {
-            spawn_ball(cmds);
+            spawn_ball(cmds, tuning.initial_weight);
             anim::spawn_klod_visuals(cmds, &asset_server);
         })
         .id();
@@ -201,6 +225,11 @@ pub(crate) struct Agglomerable {
     pub(crate) weight: f32,
 }
 
+/// An agglomerable knocked off the klod, it can't be agglomerated again
+/// until the timer finishes.
+#[derive(Component)]
+struct KnockedOff(Timer);
+
 fn transform_relative_to(point: &GlobalTransform, reference: &GlobalTransform) -> Transform {
     let relative_affine = reference.affine().inverse() * point.affine();
     let (scale, rotation, translation) = relative_affine.to_scale_rotation_translation();
@@ -210,6 +239,7 @@ fn transform_relative_to(point: &GlobalTransform, reference: &GlobalTransform) -
 fn agglo_to_klod(
     mut cmds: Commands,
     mut events: EventReader<AgglomerateToKlod>,
+    mut level_events: EventWriter<LevelEvent>,
     agglo_query: Query<
         (
             &Collider,
@@ -218,28 +248,45 @@ fn agglo_to_klod(
             &Friction,
             &Restitution,
             Option<&Name>,
+            Option<&ColliderScale>,
         ),
         With<Agglomerable>,
     >,
     mut klod_query: Query<(&mut Klod, &Velocity)>,
+    mut rejections: EventWriter<SlurpRejected>,
+    elems: Query<(&KlodElem, &Power)>,
     transforms: Query<&GlobalTransform>,
+    tuning: Res<KlodTuning>,
+    rules: Res<SlurpRules>,
+    mut klod_powers: Local<Vec<Power>>,
 ) {
     for &AgglomerateToKlod { klod, agglo, agglo_weight } in events.iter() {
         if let Ok((mut klod_data, klod_velocity)) = klod_query.get_mut(klod) {
             let klod_trans = transforms.get(klod).unwrap();
-            let (coll, agglo_trans, power, friction, restitution, name) =
+            let (coll, agglo_trans, power, friction, restitution, name, coll_scale) =
                 match agglo_query.get(agglo) {
                     Ok(item) => item,
                     _ => continue,
                 };
             let mut trans = transform_relative_to(agglo_trans, klod_trans);
             trans.translation = trans.translation * 0.8;
-            let within_radius = || {
-                let distance_to_center = trans.translation.length();
-                klod_data.within_radius(distance_to_center)
+            let agglo_volume = coll.raw.mass_properties(1.0).mass();
+            klod_powers.clear();
+            let powers = elems.iter().filter(|(elem, _)| elem.klod == klod);
+            klod_powers.extend(powers.map(|(_, power)| *power));
+            let mut slurp = Slurp {
+                klod: &klod_data,
+                klod_velocity: klod_velocity.linvel,
+                klod_powers: &klod_powers,
+                distance: trans.translation.length(),
+                weight: agglo_weight,
+                volume: agglo_volume,
+                name: name.map(Name::as_str),
+                tuning: &tuning,
+                weight_bonus: 1.0,
             };
-            let can_slurp = || klod_data.can_slurp(agglo_weight, klod_velocity.linvel);
-            if !within_radius() || !can_slurp() {
+            if let Err(reason) = rules.check(&mut slurp) {
+                rejections.send(SlurpRejected { klod, agglo, reason });
                 continue;
             }
             cmds.entity(agglo)
@@ -248,11 +295,15 @@ fn agglo_to_klod(
                 .insert_bundle((trans, KlodVisualElem));
             cmds.entity(klod).add_child(agglo);
             klod_data.weight += agglo_weight;
+            klod_data.volume += agglo_volume;
+            if let Some(name) = name {
+                level_events.send(LevelEvent::Agglomerated(name.to_string()));
+            }
 
             let name = name.map_or("Klod elem".to_owned(), |name| name.to_string() + " elem");
             screen_print!("added {name} to klod {klod:?}");
             cmds.entity(klod).add_children(|cmds| {
-                spawn_klod_elem(
+                let mut elem = spawn_klod_elem(
                     cmds,
                     name,
                     KlodElem { klod, scene: Some(agglo) },
@@ -263,13 +314,17 @@ fn agglo_to_klod(
                     *restitution,
                     *power,
                 );
+                // The collider has the scale of the agglomerable baked in.
+                if let Some(coll_scale) = coll_scale {
+                    elem.insert(*coll_scale);
+                }
             });
         }
     }
 }
 fn shlurp_agglomerable(
     klod: Query<&KlodElem>,
-    agglo: Query<&Agglomerable>,
+    agglo: Query<&Agglomerable, Without<KnockedOff>>,
     mut events: EventWriter<AgglomerateToKlod>,
     mut collisions: EventReader<ContactForceEvent>,
 ) {
@@ -289,6 +344,102 @@ fn shlurp_agglomerable(
     }
 }
 
+/// Detach the outermost items of the klod on hard impacts, reversing
+/// [`agglo_to_klod`].
+fn knock_off_elems(
+    mut cmds: Commands,
+    mut collisions: EventReader<ContactForceEvent>,
+    mut klod_query: Query<(&mut Klod, &Velocity, &GlobalTransform)>,
+    elems: Query<(
+        Entity,
+        &KlodElem,
+        &Transform,
+        &Collider,
+        &ColliderMassProperties,
+        &Friction,
+        &Restitution,
+        &Power,
+    )>,
+    transforms: Query<&GlobalTransform>,
+    time: Res<Time>,
+    mut cooldown: Local<f64>,
+) {
+    let mut strongest: Option<(Entity, f32)> = None;
+    for event in collisions.iter() {
+        let klod = match (elems.get(event.collider1), elems.get(event.collider2)) {
+            (Ok((_, elem, ..)), _) | (_, Ok((_, elem, ..))) => elem.klod,
+            _ => continue,
+        };
+        let magnitude = event.total_force_magnitude;
+        if strongest.map_or(true, |(_, strongest)| magnitude > strongest) {
+            strongest = Some((klod, magnitude));
+        }
+    }
+    let (klod, magnitude) = match strongest {
+        Some((klod, magnitude)) if magnitude > KNOCK_OFF_FORCE => (klod, magnitude),
+        _ => return,
+    };
+    if time.seconds_since_startup() < *cooldown {
+        return;
+    }
+    let (mut klod_data, klod_velocity, klod_trans) = match klod_query.get_mut(klod) {
+        Ok(item) => item,
+        Err(_) => return,
+    };
+    let mut outermost: Vec<_> = elems
+        .iter()
+        .filter(|(_, elem, ..)| elem.klod == klod && elem.scene.is_some())
+        .collect();
+    outermost.sort_by_key(|(_, _, trans, ..)| Reverse(FloatOrd(trans.translation.length())));
+    let count = ((magnitude / KNOCK_OFF_FORCE) as usize).min(MAX_KNOCKED_OFF);
+    for (elem, klod_elem, _, coll, mass, friction, restitution, power) in
+        outermost.into_iter().take(count)
+    {
+        let (agglo, mass) = match (klod_elem.scene, mass) {
+            (Some(agglo), ColliderMassProperties::Mass(mass)) => (agglo, *mass),
+            _ => continue,
+        };
+        let agglo_trans = match transforms.get(agglo) {
+            Ok(trans) => trans.compute_transform(),
+            Err(_) => continue,
+        };
+        *cooldown = time.seconds_since_startup() + KNOCK_OFF_COOLDOWN;
+        klod_data.weight -= mass;
+        klod_data.volume -= coll.raw.mass_properties(1.0).mass();
+        screen_print!("knocked {agglo:?} off klod {klod:?}");
+
+        let away = (agglo_trans.translation - klod_trans.translation()).normalize_or_zero();
+        cmds.entity(elem).despawn_recursive();
+        cmds.entity(klod).remove_children(&[agglo]);
+        cmds.entity(agglo)
+            .remove::<KlodVisualElem>()
+            .insert_bundle(AggloBundle::new(mass, *power))
+            .insert_bundle((
+                coll.clone(),
+                *friction,
+                *restitution,
+                agglo_trans,
+                Velocity {
+                    linvel: klod_velocity.linvel + away * KNOCK_OFF_SPEED,
+                    ..default()
+                },
+                KnockedOff(Timer::from_seconds(KNOCKED_OFF_SECONDS, false)),
+            ));
+    }
+}
+
+fn recover_knocked_off(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut knocked_off: Query<(Entity, &mut KnockedOff)>,
+) {
+    for (entity, mut knocked_off) in &mut knocked_off {
+        if knocked_off.0.tick(time.delta()).finished() {
+            cmds.entity(entity).remove::<KnockedOff>();
+        }
+    }
+}
+
 fn ball_input(
     keys: Res<Input<KeyCode>>,
     gp_axis: Res<Axis<GamepadAxis>>,
@@ -298,6 +449,7 @@ fn ball_input(
     time: Res<Time>,
     mut pound_timeout: Local<f64>,
     uses_gamepad: Res<UsesGamepad>,
+    tuning: Res<KlodTuning>,
 ) {
     use KeyCode::{Down, Left, Right, Up, A, D, S, W};
 
@@ -317,8 +469,8 @@ fn ball_input(
     let gp_force = gp_x_force + gp_y_force;
     let cam_rot = camera.single();
     let vel = velocity.linvel;
-    let additional_weight = klod.weight - KLOD_INITIAL_WEIGHT;
-    let force = BASE_INPUT_IMPULSE + additional_weight * INPUT_WEIGHT_COMP;
+    let additional_weight = klod.weight - klod.initial_weight;
+    let force = tuning.input_impulse + additional_weight * tuning.input_weight_comp;
     let force = |key, dir| if keys.pressed(key) { dir * force } else { Vec2::ZERO };
     let force = if gp_force.length_squared() > 0.01 && uses_gamepad.yes {
         gp_force * 1.2
@@ -329,15 +481,52 @@ fn ball_input(
             + (force(D, -Vec2::X) + force(Right, -Vec2::X))
     };
     let force = Vec2::from_angle(-cam_rot.horizontal_rotation()).rotate(force);
-    let max_more_force = MAX_KLOD_SPEED - vel.y;
+    let max_more_force = tuning.max_speed - vel.y;
     let force = (vel.xz() + force).clamp_length_max(max_more_force) - vel.xz();
     impulse.impulse = Vec3::new(force.x, 0.0, force.y);
 
     let gp_a = gp_button(GamepadButtonType::South);
     let ground_pound = keys.just_pressed(KeyCode::Space) || gp_buttons.just_pressed(gp_a);
     if ground_pound && time.seconds_since_startup() > *pound_timeout {
-        *pound_timeout = time.seconds_since_startup() + 3.0;
-        velocity.linvel.y -= 50.0;
+        *pound_timeout = time.seconds_since_startup() + tuning.pound_cooldown;
+        velocity.linvel.y -= tuning.pound_velocity;
+    }
+}
+
+/// Grow the klod ball, its visuals and the camera distance toward the klod volume.
+fn grow_klod(
+    time: Res<Time>,
+    mut klod: Query<&mut Klod>,
+    mut balls: Query<&mut Collider, With<KlodBall>>,
+    mut visuals: Query<&mut Animate, Or<(With<KlodBallVisual>, With<KlodVisualElem>)>>,
+    mut camera: Query<&mut OrbitCamera>,
+) {
+    let mut klod = match klod.get_single_mut() {
+        Ok(klod) => klod,
+        Err(_) => return,
+    };
+    let difference = klod.target_radius() - klod.radius;
+    if difference.abs() < 0.001 {
+        return;
+    }
+    let step = KLOD_GROWTH_SPEED * time.delta_seconds();
+    let old_radius = klod.radius;
+    klod.radius += difference.clamp(-step, step);
+    let radius = klod.radius;
+    for mut camera in &mut camera {
+        camera.scale_distance(radius / old_radius);
+    }
+    for mut collider in &mut balls {
+        *collider = Collider::ball(radius);
+    }
+    for mut animate in &mut visuals {
+        match &mut *animate {
+            Animate::ResizeTo { target, .. } => *target = Vec3::splat(radius / KLOD_INITIAL_RADIUS),
+            Animate::MoveToward { target, .. } => {
+                *target = target.normalize_or_zero() * radius * 0.8
+            }
+            _ => {}
+        }
     }
 }
 
@@ -402,16 +591,25 @@ impl BevyPlugin for Plugin {
     fn build(&self, app: &mut App) {
         #[cfg(feature = "debug")]
         app.register_inspectable::<Klod>()
-            .register_inspectable::<Agglomerable>();
+            .register_inspectable::<Agglomerable>()
+            .add_plugin(bevy_inspector_egui::InspectorPlugin::<KlodTuning>::new());
 
         // No idea why, but this system crashes the game when editor feature is enabled
         #[cfg(not(feature = "editor"))]
         app.add_system_set(GameState::Playing.on_enter(reset_scene.exclusive_system().at_start()));
 
-        app.init_resource::<KlodSpawnTransform>()
+        app.add_asset::<KlodTuning>()
+            .init_asset_loader::<tuning::KlodTuningLoader>()
+            .init_resource::<KlodTuning>()
+            .init_resource::<tuning::TuningFile>()
+            .init_resource::<tuning::LevelTuning>()
+            .add_system(tuning::update_tuning)
+            .init_resource::<KlodSpawnTransform>()
             .init_resource::<UsesGamepad>()
             .add_event::<AgglomerateToKlod>()
             .add_event::<anim::DestroyKlodEvent>()
+            .add_event::<SlurpRejected>()
+            .init_resource::<SlurpRules>()
             .add_startup_system(spawn_camera)
             .add_system_set(GameState::Playing.on_exit(lock_camera))
             .add_system_set(
@@ -421,11 +619,19 @@ impl BevyPlugin for Plugin {
             )
             .add_system_set(
                 GameState::Playing
-                    .on_update(ball_input)
+                    .on_update(ball_input.label(BallSystems::Input))
                     .with_system(anim::destroy_klod.label(BallSystems::DestroyKlod))
                     .with_system(set_freefall.label(BallSystems::FreeFallUpdate))
                     .with_system(shlurp_agglomerable)
-                    .with_system(agglo_to_klod.after(shlurp_agglomerable)),
+                    .with_system(agglo_to_klod.after(shlurp_agglomerable))
+                    .with_system(
+                        slurp::rejected_feedback
+                            .after(agglo_to_klod)
+                            .before(AudioRequestSystem),
+                    )
+                    .with_system(knock_off_elems.after(agglo_to_klod))
+                    .with_system(recover_knocked_off)
+                    .with_system(grow_klod.after(knock_off_elems)),
             );
     }
 }
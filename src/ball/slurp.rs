@@ -0,0 +1,217 @@
+//! Slurp rules, deciding whether an agglomerable touching the klod sticks to it.
+//!
+//! [`SlurpRules`] is the chain of rules of the current level: [`WithinRadius`],
+//! [`SpeedBonus`] and [`MassRatio`], followed by the [`LevelSlurpRule`]s of the
+//! level's `slurp_rules` field. The first rule to reject a slurp sends a
+//! [`SlurpRejected`] event with the reason.
+use std::fmt;
+
+use bevy::prelude::*;
+use bevy_debug_text_overlay::screen_print;
+use serde::Deserialize;
+
+use super::{tuning::KlodTuning, Klod};
+use crate::{
+    audio::{AudioAssets, AudioRequest, ImpactSound},
+    powers::Power,
+    trigger::{RunAction, TriggerAction},
+};
+
+/// Seconds between two rejected slurp sounds.
+const REJECTED_SOUND_COOLDOWN: f64 = 0.5;
+/// Seconds between two hints explaining a rejected slurp.
+const REJECTED_HINT_COOLDOWN: f64 = 6.0;
+
+/// An agglomerable touching the klod, checked by [`SlurpRule`]s.
+pub(crate) struct Slurp<'a> {
+    pub(crate) klod: &'a Klod,
+    pub(crate) klod_velocity: Vec3,
+    /// Powers of the klod ball and items already in the klod.
+    pub(crate) klod_powers: &'a [Power],
+    /// Distance of the agglomerable to the klod center.
+    pub(crate) distance: f32,
+    pub(crate) weight: f32,
+    pub(crate) volume: f32,
+    pub(crate) name: Option<&'a str>,
+    pub(crate) tuning: &'a KlodTuning,
+    /// Multiplier of the weight the klod can slurp and of the distance it
+    /// slurps from, the product of all rules' [`SlurpRule::weight_bonus`].
+    pub(crate) weight_bonus: f32,
+}
+
+/// Why an agglomerable didn't stick to the klod.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum SlurpRejection {
+    /// The agglomerable touched the klod outside its radius.
+    TooFar,
+    /// The agglomerable is too heavy for the klod at its current speed.
+    TooHeavy,
+    /// The agglomerable is too big compared to the klod.
+    TooBig,
+    /// The agglomerable can only be slurped by a klod with this power.
+    MissingPower(Power),
+}
+impl fmt::Display for SlurpRejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SlurpRejection::TooFar => write!(f, "Too far"),
+            SlurpRejection::TooHeavy => write!(f, "Too heavy"),
+            SlurpRejection::TooBig => write!(f, "Too big"),
+            SlurpRejection::MissingPower(power) => write!(f, "Needs the {power} power"),
+        }
+    }
+}
+
+/// An agglomerable touched the klod without sticking to it.
+pub(crate) struct SlurpRejected {
+    pub(crate) klod: Entity,
+    pub(crate) agglo: Entity,
+    pub(crate) reason: SlurpRejection,
+}
+
+pub(crate) trait SlurpRule: Send + Sync + 'static {
+    /// `Err` with the reason if `slurp` should not stick to the klod.
+    fn check(&self, slurp: &Slurp) -> Result<(), SlurpRejection>;
+
+    /// Multiplier of the weight the klod can slurp, see [`MassRatio`], and of
+    /// the distance it slurps from, see [`WithinRadius`].
+    fn weight_bonus(&self, _slurp: &Slurp) -> f32 {
+        1.0
+    }
+}
+
+/// Only agglomerables touching the klod within its radius stick to it, the
+/// radius is multiplied by the [`Slurp::weight_bonus`].
+pub(crate) struct WithinRadius;
+impl SlurpRule for WithinRadius {
+    fn check(&self, slurp: &Slurp) -> Result<(), SlurpRejection> {
+        let (distance, bonus, radius) = (slurp.distance, slurp.weight_bonus, slurp.klod.radius);
+        let can_slurp = distance < bonus * radius;
+        let color = if can_slurp { Color::GREEN } else { Color::RED };
+        screen_print!(col: color, "slurp dist: {distance:.3} <? {bonus:.3} * {radius:.3}");
+        if can_slurp {
+            Ok(())
+        } else {
+            Err(SlurpRejection::TooFar)
+        }
+    }
+}
+
+/// The faster the klod, the heavier and farther the agglomerables it can
+/// slurp, see [`KlodTuning::speed_bonus`].
+pub(crate) struct SpeedBonus;
+impl SlurpRule for SpeedBonus {
+    fn check(&self, _slurp: &Slurp) -> Result<(), SlurpRejection> {
+        Ok(())
+    }
+    fn weight_bonus(&self, slurp: &Slurp) -> f32 {
+        slurp.tuning.speed_bonus(slurp.klod_velocity.length())
+    }
+}
+
+/// Only agglomerables lighter than a fraction of the klod weight stick to it,
+/// multiplied by the [`Slurp::weight_bonus`].
+pub(crate) struct MassRatio;
+impl SlurpRule for MassRatio {
+    fn check(&self, slurp: &Slurp) -> Result<(), SlurpRejection> {
+        let (weight, bonus) = (slurp.weight, slurp.weight_bonus);
+        let weight_limit = slurp.klod.weight * slurp.tuning.slurp_weight_ratio;
+        let can_slurp = weight < bonus * weight_limit;
+        let color = if can_slurp { Color::GREEN } else { Color::RED };
+        screen_print!(col: color, "slurp: {weight:.3} <? {bonus:.3} * {weight_limit:.3}");
+        if can_slurp {
+            Ok(())
+        } else {
+            Err(SlurpRejection::TooHeavy)
+        }
+    }
+}
+
+/// A rule added by a level in its `slurp_rules` field.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) enum LevelSlurpRule {
+    /// Agglomerables bigger than this ratio of the klod volume don't stick.
+    SizeRatio(f32),
+    /// Agglomerables with a name starting with `items` only stick to a klod
+    /// with `power`.
+    RequiresPower { items: String, power: Power },
+}
+impl SlurpRule for LevelSlurpRule {
+    fn check(&self, slurp: &Slurp) -> Result<(), SlurpRejection> {
+        match self {
+            LevelSlurpRule::SizeRatio(ratio) if slurp.volume > slurp.klod.volume * ratio => {
+                Err(SlurpRejection::TooBig)
+            }
+            LevelSlurpRule::RequiresPower { items, power }
+                if slurp
+                    .name
+                    .map_or(false, |name| name.starts_with(items.as_str()))
+                    && !slurp.klod_powers.contains(power) =>
+            {
+                Err(SlurpRejection::MissingPower(*power))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The slurp rules of the current level, checked in order.
+pub(crate) struct SlurpRules(Vec<Box<dyn SlurpRule>>);
+impl Default for SlurpRules {
+    fn default() -> Self {
+        SlurpRules::new(Vec::new())
+    }
+}
+impl SlurpRules {
+    /// The default rules followed by the `slurp_rules` of a level.
+    pub(crate) fn new(level_rules: impl IntoIterator<Item = LevelSlurpRule>) -> Self {
+        let mut rules = SlurpRules(vec![
+            Box::new(WithinRadius),
+            Box::new(SpeedBonus),
+            Box::new(MassRatio),
+        ]);
+        for rule in level_rules {
+            rules.push(rule);
+        }
+        rules
+    }
+    pub(crate) fn push(&mut self, rule: impl SlurpRule) {
+        self.0.push(Box::new(rule));
+    }
+    /// Set the `weight_bonus` of `slurp`, then check it against each rule.
+    pub(super) fn check(&self, slurp: &mut Slurp) -> Result<(), SlurpRejection> {
+        let weight_bonus = self.0.iter().map(|rule| rule.weight_bonus(slurp)).product();
+        slurp.weight_bonus = weight_bonus;
+        self.0.iter().try_for_each(|rule| rule.check(slurp))
+    }
+}
+
+/// Play a sound and show a hint when an agglomerable doesn't stick, ignoring
+/// agglomerables merely brushing against the klod.
+pub(super) fn rejected_feedback(
+    mut rejections: EventReader<SlurpRejected>,
+    mut audio_requests: EventWriter<AudioRequest>,
+    mut actions: EventWriter<RunAction>,
+    audio: Res<AudioAssets>,
+    time: Res<Time>,
+    mut sound_cooldown: Local<f64>,
+    mut hint_cooldown: Local<f64>,
+) {
+    let now = time.seconds_since_startup();
+    for SlurpRejected { klod, agglo, reason } in rejections.iter() {
+        if *reason == SlurpRejection::TooFar {
+            continue;
+        }
+        screen_print!(sec: 1.0, col: Color::ORANGE, "klod {klod:?} rejected {agglo:?}: {reason}");
+        if now > *sound_cooldown {
+            *sound_cooldown = now + REJECTED_SOUND_COOLDOWN;
+            let sound = audio.impact(ImpactSound::SoftMedium);
+            audio_requests.send(AudioRequest::PlayEffect(sound, 0.5));
+        }
+        if matches!(reason, SlurpRejection::MissingPower(_)) && now > *hint_cooldown {
+            *hint_cooldown = now + REJECTED_HINT_COOLDOWN;
+            actions.send(RunAction(TriggerAction::Hint(reason.to_string())));
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Klod physics tuning, the numbers behind the game feel.
+//!
+//! The tuning is read from the `klod.tuning` RON file of the assets directory,
+//! reloaded when modified in `debug` builds. Levels can replace it with their
+//! own `tuning` field, fields missing from either use the [`Default`] values.
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    prelude::*,
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+#[cfg(feature = "debug")]
+use bevy_inspector_egui::Inspectable;
+use serde::Deserialize;
+
+#[cfg_attr(feature = "debug", derive(Inspectable))]
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, PartialEq, TypeUuid)]
+#[serde(default)]
+#[uuid = "7f191334-fcc2-457b-ba0c-a21bca2669b4"]
+pub(crate) struct KlodTuning {
+    /// Impulse of player input on a klod that absorbed nothing.
+    pub(crate) input_impulse: f32,
+    /// Additional input impulse per unit of absorbed weight.
+    pub(crate) input_weight_comp: f32,
+    /// Weight of the klod ball alone.
+    pub(crate) initial_weight: f32,
+    /// Speed above which player input stops accelerating the klod.
+    pub(crate) max_speed: f32,
+    /// Downward velocity added by a ground pound.
+    pub(crate) pound_velocity: f32,
+    /// Seconds between two ground pounds.
+    pub(crate) pound_cooldown: f64,
+    /// The klod slurps items lighter than its weight times this ratio,
+    /// multiplied by the speed bonus.
+    pub(crate) slurp_weight_ratio: f32,
+    /// Speed bonus of a klod rolling at `max_speed`.
+    pub(crate) slurp_speed_bonus: f32,
+    /// Speed bonus of a slow or still klod.
+    pub(crate) slurp_min_speed_bonus: f32,
+}
+impl Default for KlodTuning {
+    fn default() -> Self {
+        KlodTuning {
+            input_impulse: 1.0,
+            input_weight_comp: 0.5,
+            initial_weight: 4.2,
+            max_speed: 28.0,
+            pound_velocity: 50.0,
+            pound_cooldown: 3.0,
+            slurp_weight_ratio: 0.1,
+            slurp_speed_bonus: 1.2,
+            slurp_min_speed_bonus: 0.5,
+        }
+    }
+}
+impl KlodTuning {
+    /// Multiplier of the weight a klod rolling at `speed` can slurp.
+    pub(crate) fn speed_bonus(&self, speed: f32) -> f32 {
+        (speed * self.slurp_speed_bonus / self.max_speed).max(self.slurp_min_speed_bonus)
+    }
+}
+
+#[derive(Default)]
+pub(super) struct KlodTuningLoader;
+impl AssetLoader for KlodTuningLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let tuning: KlodTuning = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(tuning));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tuning"]
+    }
+}
+
+/// The `klod.tuning` file, used when the level has no tuning of its own.
+pub(super) struct TuningFile(Handle<KlodTuning>);
+impl FromWorld for TuningFile {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        TuningFile(assets.load("klod.tuning"))
+    }
+}
+
+/// The `tuning` of the current level, replacing the `klod.tuning` file.
+#[derive(Default)]
+pub(crate) struct LevelTuning(pub(crate) Option<KlodTuning>);
+
+/// Update the [`KlodTuning`] resource when the tuning file or level changes.
+pub(super) fn update_tuning(
+    mut tuning: ResMut<KlodTuning>,
+    mut events: EventReader<AssetEvent<KlodTuning>>,
+    level: Res<LevelTuning>,
+    file: Res<TuningFile>,
+    tunings: Res<Assets<KlodTuning>>,
+) {
+    let mut file_changed = false;
+    for event in events.iter() {
+        match event {
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                file_changed |= *handle == file.0;
+            }
+            AssetEvent::Removed { .. } => {}
+        }
+    }
+    if !file_changed && !level.is_changed() {
+        return;
+    }
+    let new_tuning = level.0.as_ref().or_else(|| tunings.get(&file.0));
+    *tuning = new_tuning.cloned().unwrap_or_default();
+    info!("Klod tuning updated: {:?}", *tuning);
+}
@@ -0,0 +1,23 @@
+//! Convert level files between the `.klodlvl` and `.klodbin` formats.
+//!
+//! Usage: `klod-convert <FROM> <TO>`, the format is chosen by the file extensions.
+use std::{env, process::ExitCode};
+
+fn main() -> ExitCode {
+    let args: Vec<_> = env::args_os().skip(1).collect();
+    let (from, to) = match args.as_slice() {
+        [from, to] => (from, to),
+        _ => {
+            eprintln!("Usage: klod-convert <FROM> <TO>");
+            return ExitCode::from(2);
+        }
+    };
+    match klod::convert_level(from, to) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            let from = from.to_string_lossy();
+            eprintln!("{from}: could not convert level: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,29 @@
+//! Check level files for common mistakes without opening a game window.
+//!
+//! Usage: `klod-lint <LEVEL>...`, exits with an error code if any level has problems.
+use std::{env, process::ExitCode};
+
+fn main() -> ExitCode {
+    let levels: Vec<_> = env::args_os().skip(1).collect();
+    if levels.is_empty() {
+        eprintln!("Usage: klod-lint <LEVEL>...");
+        return ExitCode::from(2);
+    }
+    let mut success = true;
+    for level in &levels {
+        let name = level.to_string_lossy();
+        let issues = klod::lint_level(level);
+        if issues.is_empty() {
+            println!("{name}: ok");
+        }
+        for issue in &issues {
+            success = false;
+            println!("{name}: {issue}");
+        }
+    }
+    if success {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
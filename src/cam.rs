@@ -39,6 +39,11 @@ impl OrbitCamera {
     pub(crate) fn horizontal_rotation(&self) -> f32 {
         self.x_rot % TAU
     }
+    /// Move away from the followed entity as it grows, `scale` is how much
+    /// it grew since the last call.
+    pub(crate) fn scale_distance(&mut self, scale: f32) {
+        self.distance *= scale;
+    }
     pub(crate) fn follows(entity: Entity) -> Self {
         OrbitCamera {
             x_rot: 1.48,
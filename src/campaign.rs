@@ -0,0 +1,186 @@
+//! Campaign mode, a sequence of levels each unlocked by winning the previous one.
+//!
+//! The levels are listed in order in the `main.campaign` RON file of the assets
+//! directory. The progress is saved in `campaign_progress.ron`, next to the
+//! assets directory.
+use bevy::{
+    asset::{AssetLoader, LoadContext, LoadedAsset},
+    ecs::system::Command,
+    prelude::{Plugin as BevyPlugin, *},
+    reflect::TypeUuid,
+    utils::BoxedFuture,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    ball::Klod,
+    scene::{level_file, KlodScene},
+    score::{GameData, Score},
+    state::GameState,
+    system_helper::EasySystemSetCtor,
+};
+
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct CampaignLevel {
+    /// Path of the `.klodlvl` file in the assets directory, builds without
+    /// the `debug` feature load the `.klodbin` file of the same name, see
+    /// [`level_file`].
+    level: String,
+    /// Replaces the `required_score` of the level.
+    required_score: f32,
+}
+impl CampaignLevel {
+    /// Path of the level file loaded by this build.
+    pub(crate) fn path(&self) -> String {
+        level_file(&self.level)
+    }
+}
+
+#[derive(Deserialize, Debug, TypeUuid)]
+#[uuid = "b1f2c7a4-93d0-4e85-a6b3-2c8d5e71f049"]
+pub(crate) struct Campaign {
+    pub(crate) levels: Vec<CampaignLevel>,
+}
+
+#[derive(Default)]
+struct CampaignLoader;
+impl AssetLoader for CampaignLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let campaign: Campaign = ron::de::from_bytes(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(campaign));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["campaign"]
+    }
+}
+
+/// The campaign played in campaign mode.
+pub(crate) struct CampaignHandle(Handle<Campaign>);
+impl FromWorld for CampaignHandle {
+    fn from_world(world: &mut World) -> Self {
+        let assets = world.resource::<AssetServer>();
+        CampaignHandle(assets.load("main.campaign"))
+    }
+}
+impl CampaignHandle {
+    /// The campaign levels, empty until the campaign file is loaded.
+    pub(crate) fn levels<'a>(&self, campaigns: &'a Assets<Campaign>) -> &'a [CampaignLevel] {
+        campaigns.get(&self.0).map_or(&[], |c| &c.levels)
+    }
+}
+
+/// The campaign level being played, absent when playing a level outside of
+/// campaign mode.
+pub(crate) struct PlayingCampaign {
+    pub(crate) index: usize,
+    pub(crate) required_score: f32,
+}
+
+/// How far the player went in the campaign.
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct CampaignProgress {
+    /// How many levels are unlocked, the first level is always unlocked.
+    unlocked: usize,
+}
+impl Default for CampaignProgress {
+    fn default() -> Self {
+        Self::read().unwrap_or(CampaignProgress { unlocked: 1 })
+    }
+}
+impl CampaignProgress {
+    pub(crate) fn is_unlocked(&self, index: usize) -> bool {
+        index < self.unlocked
+    }
+    /// Unlock the level at `index` and all levels before it, `false` if it already was.
+    fn unlock(&mut self, index: usize) -> bool {
+        let was_unlocked = self.is_unlocked(index);
+        self.unlocked = self.unlocked.max(index + 1);
+        !was_unlocked
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn file() -> std::path::PathBuf {
+        crate::scene::get_base_path().with_file_name("campaign_progress.ron")
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    fn read() -> Option<Self> {
+        let data = std::fs::read(Self::file()).ok()?;
+        ron::de::from_bytes(&data)
+            .map_err(|err| error!("Can't read the campaign progress: {err}"))
+            .ok()
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save(&self) {
+        let result = ron::ser::to_string(self)
+            .map_err(|err| err.to_string())
+            .and_then(|data| std::fs::write(Self::file(), data).map_err(|err| err.to_string()));
+        if let Err(err) = result {
+            error!("Can't save the campaign progress: {err}");
+        }
+    }
+    /// The web build has no file system, progress is lost on reload.
+    #[cfg(target_arch = "wasm32")]
+    fn read() -> Option<Self> {
+        None
+    }
+    #[cfg(target_arch = "wasm32")]
+    fn save(&self) {}
+}
+
+/// Play the campaign level at the given index.
+pub(crate) struct StartCampaignLevel(pub(crate) usize);
+impl Command for StartCampaignLevel {
+    fn write(self, world: &mut World) {
+        let campaigns = world.resource::<Assets<Campaign>>();
+        let levels = world.resource::<CampaignHandle>().levels(campaigns);
+        let level = match levels.get(self.0) {
+            Some(level) => level,
+            None => {
+                error!("There is no campaign level {}", self.0);
+                return;
+            }
+        };
+        let path = level.path();
+        let playing = PlayingCampaign {
+            index: self.0,
+            required_score: level.required_score,
+        };
+        KlodScene::load(world, path);
+        world.insert_resource(playing);
+    }
+}
+
+fn unlock_next_level(
+    playing: Option<Res<PlayingCampaign>>,
+    mut progress: ResMut<CampaignProgress>,
+    timer: Res<GameData>,
+    klod: Query<&Klod>,
+) {
+    let (playing, klod) = match (playing, klod.get_single()) {
+        (Some(playing), Ok(klod)) => (playing, klod),
+        _ => return,
+    };
+    if Score::new(&timer, klod).won() && progress.unlock(playing.index + 1) {
+        info!("Unlocked campaign level {}", playing.index + 1);
+        progress.save();
+    }
+}
+
+pub(crate) struct Plugin;
+impl BevyPlugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<Campaign>()
+            .init_asset_loader::<CampaignLoader>()
+            .init_resource::<CampaignHandle>()
+            .init_resource::<CampaignProgress>()
+            .add_system_set(GameState::GameComplete.on_enter(unlock_next_level));
+    }
+}
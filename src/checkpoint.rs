@@ -0,0 +1,170 @@
+//! Checkpoints, where the klod respawns when the player asks for it or when
+//! it enters a kill zone.
+//!
+//! Touching a checkpoint moves the [`KlodSpawnTransform`] to its center.
+//! Holding R or the gamepad start button for a second respawns the klod
+//! there, at the cost of some time but keeping what it collected.
+use bevy::prelude::{Plugin as BevyPlugin, *};
+use bevy_debug_text_overlay::screen_print;
+use bevy_rapier3d::prelude::{RapierContext, Velocity};
+use serde::Deserialize;
+
+use crate::{
+    ball::{Klod, KlodBall, KlodSpawnTransform},
+    score::GameData,
+    state::GameState,
+    system_helper::EasySystemSetCtor,
+    EndReason, GameOver,
+};
+
+/// A sensor volume recording where the klod respawns.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub(crate) struct Checkpoint;
+
+/// A sensor volume the klod should never enter, such as the void below the level.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub(crate) struct KillZone;
+
+/// What happens when the klod enters a [`KillZone`].
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KillZoneAction {
+    /// Respawn at the last checkpoint, as if the player asked for it.
+    Respawn,
+    /// Lose the game, with [`EndReason::KillZone`].
+    GameOver,
+}
+
+/// How respawning works in a level.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub(crate) struct RespawnRules {
+    /// Seconds removed from the timer on each respawn.
+    pub(crate) penalty: f32,
+    pub(crate) kill_zone: KillZoneAction,
+}
+impl Default for RespawnRules {
+    fn default() -> Self {
+        RespawnRules { penalty: 10.0, kill_zone: KillZoneAction::Respawn }
+    }
+}
+
+/// Move the klod back to the last checkpoint reached.
+pub(crate) struct RespawnKlod;
+
+/// Whether the klod was inside a [`KillZone`] last frame.
+#[derive(Default)]
+struct InKillZone(bool);
+
+fn reach_checkpoint(
+    mut spawn_point: ResMut<KlodSpawnTransform>,
+    checkpoints: Query<(Entity, &GlobalTransform), With<Checkpoint>>,
+    klods_query: Query<Entity, With<KlodBall>>,
+    rapier_context: Res<RapierContext>,
+    mut klods: Local<Vec<Entity>>,
+) {
+    klods.extend(&klods_query);
+    for (checkpoint, transform) in &checkpoints {
+        let not_checkpoint = |e1, e2| (e1 == checkpoint).then(|| e2).unwrap_or(e1);
+        let klod_at_checkpoint = rapier_context
+            .intersections_with(checkpoint)
+            .any(|(e1, e2, colliding)| colliding && klods.contains(&not_checkpoint(e1, e2)));
+        let respawn = Transform::from_translation(transform.translation());
+        if klod_at_checkpoint && spawn_point.0 != respawn {
+            screen_print!("Reached checkpoint");
+            spawn_point.0 = respawn;
+        }
+    }
+    klods.clear();
+}
+
+fn enter_kill_zone(
+    mut respawn: EventWriter<RespawnKlod>,
+    mut game_over: EventWriter<GameOver>,
+    rules: Res<RespawnRules>,
+    kill_zones: Query<Entity, With<KillZone>>,
+    klods_query: Query<Entity, With<KlodBall>>,
+    rapier_context: Res<RapierContext>,
+    mut klods: Local<Vec<Entity>>,
+    mut was_inside: ResMut<InKillZone>,
+) {
+    klods.extend(&klods_query);
+    let inside = kill_zones.iter().any(|kill_zone| {
+        let not_zone = |e1, e2| (e1 == kill_zone).then(|| e2).unwrap_or(e1);
+        rapier_context
+            .intersections_with(kill_zone)
+            .any(|(e1, e2, colliding)| colliding && klods.contains(&not_zone(e1, e2)))
+    });
+    // Only react when entering, the klod stays inside until it is moved out.
+    if inside && !was_inside.0 {
+        screen_print!("Entered kill zone");
+        match rules.kill_zone {
+            KillZoneAction::Respawn => respawn.send(RespawnKlod),
+            KillZoneAction::GameOver => game_over.send(GameOver(EndReason::KillZone)),
+        }
+    }
+    was_inside.0 = inside;
+    klods.clear();
+}
+
+/// A new game starts outside of kill zones, whatever happened in the last one.
+fn leave_kill_zone(mut in_kill_zone: ResMut<InKillZone>) {
+    in_kill_zone.0 = false;
+}
+
+fn respawn_input(
+    time: Res<Time>,
+    mut held_down: Local<f32>,
+    mut respawn: EventWriter<RespawnKlod>,
+    gp_buttons: Res<Input<GamepadButton>>,
+    keys: Res<Input<KeyCode>>,
+) {
+    let gp_button = |button_type| GamepadButton { gamepad: Gamepad { id: 0 }, button_type };
+    let gp_start = gp_button(GamepadButtonType::Start);
+    let reset = keys.pressed(KeyCode::R) || gp_buttons.pressed(gp_start);
+    if reset {
+        *held_down += time.delta_seconds();
+    } else {
+        *held_down = 0.0;
+    }
+    if *held_down >= 1.0 {
+        *held_down = 0.0;
+        respawn.send(RespawnKlod);
+    }
+}
+
+fn respawn_klod(
+    mut events: EventReader<RespawnKlod>,
+    mut klod: Query<(&mut Transform, &mut Velocity), With<Klod>>,
+    mut timer: ResMut<GameData>,
+    spawn_point: Res<KlodSpawnTransform>,
+    rules: Res<RespawnRules>,
+) {
+    if events.iter().count() == 0 {
+        return;
+    }
+    if let Ok((mut transform, mut velocity)) = klod.get_single_mut() {
+        screen_print!("Respawned at last checkpoint");
+        *transform = spawn_point.0;
+        *velocity = default();
+        timer.penalize(rules.penalty);
+    }
+}
+
+pub(crate) struct Plugin;
+impl BevyPlugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<RespawnRules>()
+            .init_resource::<InKillZone>()
+            .add_event::<RespawnKlod>()
+            .add_system_set(GameState::Playing.on_enter(leave_kill_zone))
+            .add_system_set(
+                GameState::Playing
+                    .on_update(reach_checkpoint)
+                    .with_system(respawn_input)
+                    .with_system(enter_kill_zone)
+                    .with_system(respawn_klod.after(respawn_input).after(enter_kill_zone)),
+            );
+    }
+}
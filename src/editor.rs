@@ -6,7 +6,6 @@ use bevy::{
     ecs::system::SystemState,
     hierarchy::despawn_with_children_recursive,
     prelude::{Plugin as BevyPlugin, *},
-    ui::FocusPolicy,
 };
 
 use bevy_editor_pls::{
@@ -19,8 +18,8 @@ use bevy_editor_pls_default_windows::{
     hierarchy::{picking::IgnoreEditorRayCast, HideInEditor, HierarchyState, HierarchyWindow},
 };
 use bevy_inspector_egui::{egui, options::OptionAttributes, Inspectable};
-use bevy_mod_picking::{DefaultPickingPlugins, PickableMesh, PickingCameraBundle, Selection};
-use bevy_rapier3d::prelude::{Collider, RapierConfiguration, Sensor};
+use bevy_mod_picking::{DefaultPickingPlugins, PickingCameraBundle};
+use bevy_rapier3d::prelude::RapierConfiguration;
 use bevy_rapier3d::render::DebugLinesMesh;
 use bevy_transform_gizmo::{
     GizmoPickSource, InternalGizmoCamera, PickableGizmo, TransformGizmo, TransformGizmoPlugin,
@@ -29,13 +28,18 @@ use bevy_transform_gizmo::{
 use crate::{
     audio::{ImpactSound, IntroTrack, MusicTrack},
     cam::OrbitCamera,
-    collision_groups as groups,
+    force::ForceKind,
     game_audio::MusicTrigger,
+    kinematic::{Easing, Keyframe},
     powers::Power,
-    prefabs::{AggloData, Scenery, SerdeCollider},
+    prefabs::{
+        AggloData, CheckpointData, ForceVolumeData, KillZoneData, KinematicData, MusicTriggerData,
+        Prefab, Scenery, SerdeCollider, TimePickupData, TriggerZoneData,
+    },
     scene::{reset_scene, save_scene, KlodScene, ObjectType, PhysicsObject},
     state::GameState,
     system_helper::EasySystemSetCtor,
+    trigger::TriggerWhen,
 };
 
 fn toggle_editor_active(
@@ -80,6 +84,15 @@ macro_rules! err_sys {
     };
 }
 
+/// Kind of prop spawned by the "Add new prop" button.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PropKind {
+    /// Agglomerable, or scenery when it has no name or mass.
+    Physics,
+    Kinematic,
+    TimePickup,
+}
+
 pub struct SceneWindowState {
     filename: String,
     scene: String,
@@ -90,7 +103,11 @@ pub struct SceneWindowState {
     music: MusicTrack,
     music_start: Option<IntroTrack>,
     power: Power,
+    kind: PropKind,
+    pickup_seconds: f32,
     scene_save_result: Option<Result<(), Box<dyn std::error::Error + Send + Sync>>>,
+    /// Whether the level loaded with the "Load" button is still loading.
+    loading: bool,
 }
 impl Default for SceneWindowState {
     fn default() -> Self {
@@ -104,7 +121,10 @@ impl Default for SceneWindowState {
             music: default(),
             music_start: default(),
             power: default(),
+            kind: PropKind::Physics,
+            pickup_seconds: 10.0,
             scene_save_result: default(),
+            loading: false,
         }
     }
 }
@@ -148,7 +168,13 @@ impl EditorWindow for SceneWindow {
                         state.scene_save_result = Some(KlodScene::save(world, &filename));
                     }
                     if ui.button("Load").clicked() {
-                        state.scene_save_result = Some(KlodScene::load(world, &filename));
+                        KlodScene::load(world, level_name(&state.filename));
+                        state.scene_save_result = None;
+                        state.loading = true;
+                    }
+                    if state.loading {
+                        state.scene_save_result = KlodScene::load_result(world);
+                        state.loading = state.scene_save_result.is_none();
                     }
                     ui.end_row();
 
@@ -217,6 +243,28 @@ impl EditorWindow for SceneWindow {
                         .clamp_range(0.0..=2.0),
                 );
                 ui.end_row();
+                ui.label("Kind");
+                let kinds = [
+                    ("Physics", PropKind::Physics),
+                    ("Kinematic", PropKind::Kinematic),
+                    ("Time pickup", PropKind::TimePickup),
+                ];
+                let selected = kinds.iter().find(|(_, kind)| *kind == state.kind);
+                let ret = egui::ComboBox::from_id_source("Prop kind")
+                    .selected_text(selected.map_or("", |(name, _)| *name))
+                    .show_ui(ui, |ui| {
+                        for (name, kind) in kinds {
+                            ui.selectable_value(&mut state.kind, kind, name);
+                        }
+                    });
+                ret.response.on_hover_text(
+                    "Kinematic props follow a looping path, edit it in the Inspector. \
+                    Time pickups add seconds to the timer when the klod touches them.",
+                );
+                ui.end_row();
+                ui.label("Pickup seconds");
+                ui.add(egui::DragValue::new(&mut state.pickup_seconds).clamp_range(0.0..=600.0));
+                ui.end_row();
             });
             ui.vertical(|ui| {
                 ui.set_width(160.0);
@@ -246,52 +294,121 @@ impl EditorWindow for SceneWindow {
                 if ui.button("Spawn music trigger area").clicked() {
                     spawn_music_trigger(world, state.music, state.music_start, state.name.clone());
                 }
+                if ui.button("Spawn checkpoint").clicked() {
+                    spawn_checkpoint(world, state.name.clone());
+                }
+                if ui.button("Spawn kill zone").clicked() {
+                    spawn_kill_zone(world, state.name.clone());
+                }
+                let res = ui.button("Spawn trigger zone");
+                if res.clicked() {
+                    spawn_trigger_zone(world, state.name.clone());
+                }
+                res.on_hover_text("Set its actions in the level file");
+                let res = ui.button("Spawn water volume");
+                if res.clicked() {
+                    spawn_force_volume(world, state.name.clone());
+                }
+                res.on_hover_text("Change it to a wind or gravity volume in the level file");
             });
         });
     }
 }
 
+/// `name`, or `default` if the user didn't fill in the name field.
+fn name_or(name: String, default: &str) -> String {
+    if name.is_empty() {
+        default.to_owned()
+    } else {
+        name
+    }
+}
+
+/// Spawn `prefab`, a sensor volume, with a mesh of its `collider` so that it
+/// can be seen and selected in the editor.
+fn spawn_sensor_prefab(world: &mut World, prefab: impl Prefab, collider: &SerdeCollider) {
+    let mesh = world
+        .resource_mut::<Assets<Mesh>>()
+        .add(collider.clone().into());
+    let mut system_state = SystemState::<Commands>::new(world);
+    let mut cmds = system_state.get_mut(world);
+    let mut entity = cmds.spawn();
+    entity.insert(mesh);
+    prefab.spawn(&mut entity);
+    system_state.apply(world);
+}
+
 fn spawn_music_trigger(
     world: &mut World,
     track: MusicTrack,
     intro: Option<IntroTrack>,
     name: String,
 ) {
-    let name = if name.is_empty() {
-        "Music trigger".to_owned()
-    } else {
-        name
-    };
+    let name = name_or(name, "Music trigger");
     let collider = SerdeCollider::Cuboid { half_extents: Vec3::splat(30.0) };
-    world.resource_scope(|world, mut meshes: Mut<Assets<Mesh>>| {
-        world.spawn().insert_bundle((
-            Name::new(name),
-            MusicTrigger { intro, track },
-            Sensor,
-            groups::MUSIC,
-            Transform::default(),
-            GlobalTransform::default(),
-            Visibility::default(),
-            ComputedVisibility::default(),
-            Collider::from(collider.clone()),
-            meshes.add(collider.into()),
-            PickableMesh::default(),
-            Interaction::default(),
-            FocusPolicy::default(),
-            Selection::default(),
-            bevy_transform_gizmo::GizmoTransformable,
-        ));
-    });
+    let trigger = MusicTrigger { intro, track };
+    let music_trigger = MusicTriggerData::new(name, trigger, collider.clone());
+    spawn_sensor_prefab(world, music_trigger, &collider);
 }
 
-fn file_name(filename: &str) -> PathBuf {
-    let root = crate::scene::get_base_path();
-    let filename = if filename.is_empty() {
+fn spawn_checkpoint(world: &mut World, name: String) {
+    let name = name_or(name, "Checkpoint");
+    let collider = SerdeCollider::Cuboid { half_extents: Vec3::splat(10.0) };
+    let checkpoint = CheckpointData {
+        name,
+        collider: collider.clone(),
+        transform: default(),
+    };
+    spawn_sensor_prefab(world, checkpoint, &collider);
+}
+
+fn spawn_kill_zone(world: &mut World, name: String) {
+    let name = name_or(name, "Kill zone");
+    let collider = SerdeCollider::Cuboid { half_extents: Vec3::new(100.0, 10.0, 100.0) };
+    let kill_zone = KillZoneData {
+        name,
+        collider: collider.clone(),
+        transform: default(),
+    };
+    spawn_sensor_prefab(world, kill_zone, &collider);
+}
+
+fn spawn_trigger_zone(world: &mut World, name: String) {
+    let name = name_or(name, "Trigger zone");
+    let collider = SerdeCollider::Cuboid { half_extents: Vec3::splat(10.0) };
+    let trigger_zone = TriggerZoneData {
+        name,
+        when: TriggerWhen::Enter,
+        actions: Vec::new(),
+        collider: collider.clone(),
+        transform: default(),
+    };
+    spawn_sensor_prefab(world, trigger_zone, &collider);
+}
+
+fn spawn_force_volume(world: &mut World, name: String) {
+    let name = name_or(name, "Water");
+    let collider = SerdeCollider::Cuboid { half_extents: Vec3::new(20.0, 5.0, 20.0) };
+    let force = ForceKind::Water { buoyancy: 0.8, drag: 1.0 };
+    let force_volume = ForceVolumeData {
+        name,
+        force,
+        collider: collider.clone(),
+        transform: default(),
+    };
+    spawn_sensor_prefab(world, force_volume, &collider);
+}
+
+fn level_name(filename: &str) -> &str {
+    if filename.is_empty() {
         DEFAULT_FILENAME
     } else {
-        &filename
-    };
-    root.join(filename)
+        filename
+    }
+}
+fn file_name(filename: &str) -> PathBuf {
+    let root = crate::scene::get_base_path();
+    root.join(level_name(filename))
 }
 fn despawn_selected(world: &mut World, hierarchy: &HierarchyState) {
     for selected in hierarchy.selected.iter() {
@@ -311,13 +428,26 @@ fn spawn_object(
         spawn_restitution,
         spawn_friction,
         power,
+        kind,
+        pickup_seconds,
         ..
     }: &SceneWindowState,
 ) {
     let mut system_state =
         SystemState::<(Commands, Res<AssetServer>, ResMut<Assets<Mesh>>)>::new(world);
     let (mut cmds, assets, mut meshes) = system_state.get_mut(world);
-    let data = if &*name == "" || *spawn_mass == 0.0 {
+    let data = if *kind == PropKind::TimePickup {
+        ObjectType::TimePickup(TimePickupData { seconds: *pickup_seconds })
+    } else if *kind == PropKind::Kinematic {
+        let keyframe = |translation| Keyframe {
+            translation,
+            duration: 3.0,
+            easing: Easing::EaseInOut,
+            ..default()
+        };
+        let path = vec![keyframe(Vec3::ZERO), keyframe(Vec3::Y * 10.0)];
+        ObjectType::Kinematic(KinematicData { path })
+    } else if &*name == "" || *spawn_mass == 0.0 {
         let power = *power;
         let weakness = if power != Power::None { vec![power] } else { Vec::new() };
         ObjectType::Scenery(Scenery { weakness })
@@ -0,0 +1,103 @@
+//! Force volumes, sensors pushing the klod and agglomerables inside them.
+//!
+//! Forces are accelerations applied as impulses proportional to the mass of
+//! the body, so they scale with the current weight of the [`Klod`] and the
+//! mass of [`Agglomerable`]s.
+use bevy::prelude::{Plugin as BevyPlugin, *};
+use bevy_rapier3d::prelude::{ExternalImpulse, RapierConfiguration, RapierContext, Velocity};
+use serde::Deserialize;
+
+use crate::{
+    ball::{Agglomerable, BallSystems, Klod, KlodElem},
+    powers::Power,
+    state::GameState,
+    system_helper::EasySystemSetCtor,
+};
+
+/// Buoyancy of a klod with the [`Power::Water`] power, enough to float.
+const WATER_POWER_BUOYANCY: f32 = 1.3;
+
+/// What a [`ForceVolume`] does to bodies inside it.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub(crate) enum ForceKind {
+    /// `buoyancy` is how much of the gravity is compensated, bodies float
+    /// above 1, and `drag` how fast bodies lose their velocity.
+    Water { buoyancy: f32, drag: f32 },
+    /// A constant acceleration, such as a wind tunnel.
+    Wind(Vec3),
+    /// Replaces the world gravity.
+    Gravity(Vec3),
+}
+impl ForceKind {
+    /// Acceleration of a body with `velocity` in a world with `gravity`.
+    fn acceleration(self, velocity: Vec3, gravity: Vec3, floats: bool) -> Vec3 {
+        match self {
+            ForceKind::Water { buoyancy, drag } => {
+                let buoyancy = if floats {
+                    buoyancy.max(WATER_POWER_BUOYANCY)
+                } else {
+                    buoyancy
+                };
+                -gravity * buoyancy - velocity * drag
+            }
+            ForceKind::Wind(acceleration) => acceleration,
+            ForceKind::Gravity(new_gravity) => new_gravity - gravity,
+        }
+    }
+}
+
+/// A sensor volume applying a [`ForceKind`] to bodies inside it.
+#[derive(Component, Debug, Clone, Copy)]
+pub(crate) struct ForceVolume(pub(crate) ForceKind);
+
+fn apply_forces(
+    volumes: Query<(Entity, &ForceVolume)>,
+    elems: Query<(&KlodElem, &Power)>,
+    mut bodies: Query<(
+        &mut ExternalImpulse,
+        &Velocity,
+        Option<&Klod>,
+        Option<&Agglomerable>,
+    )>,
+    rapier_context: Res<RapierContext>,
+    config: Res<RapierConfiguration>,
+    time: Res<Time>,
+    mut inside: Local<Vec<Entity>>,
+) {
+    let delta = time.delta_seconds();
+    let klod_floats = elems.iter().any(|(_, power)| *power == Power::Water);
+    for (volume, ForceVolume(force)) in &volumes {
+        let not_volume = |e1, e2| (e1 == volume).then(|| e2).unwrap_or(e1);
+        let colliders = rapier_context
+            .intersections_with(volume)
+            .filter_map(|(e1, e2, colliding)| colliding.then(|| not_volume(e1, e2)));
+        // The klod is made of several colliders, it should be pushed once.
+        for collider in colliders {
+            let body = elems.get(collider).map_or(collider, |(elem, _)| elem.klod);
+            if !inside.contains(&body) {
+                inside.push(body);
+            }
+        }
+        for body in inside.drain(..) {
+            let (mut impulse, velocity, klod, agglo) = match bodies.get_mut(body) {
+                Ok(item) => item,
+                Err(_) => continue,
+            };
+            let (mass, floats) = match (klod, agglo) {
+                (Some(klod), _) => (klod.mass(), klod_floats),
+                (_, Some(agglo)) => (agglo.weight, false),
+                _ => continue,
+            };
+            let acceleration = force.acceleration(velocity.linvel, config.gravity, floats);
+            impulse.impulse += acceleration * mass * delta;
+        }
+    }
+}
+
+pub(crate) struct Plugin;
+impl BevyPlugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(GameState::Playing.on_update(apply_forces.after(BallSystems::Input)));
+    }
+}
@@ -9,7 +9,7 @@ use serde::Deserialize;
 
 use crate::{
     audio::{AudioAssets, AudioRequest, AudioRequestSystem, ImpactSound, IntroTrack, MusicTrack},
-    ball::{BallSystems::FreeFallUpdate, FreeFall, Klod, KlodBall, MAX_KLOD_SPEED},
+    ball::{tuning::KlodTuning, BallSystems::FreeFallUpdate, FreeFall, Klod, KlodBall},
 };
 
 #[cfg(feature = "debug")]
@@ -70,7 +70,7 @@ pub(crate) struct NoiseOnHit {
     pub(crate) noises: ArrayVec<ImpactSound, 4>,
 }
 impl NoiseOnHit {
-    fn impact(&self) -> Option<ImpactSound> {
+    pub(crate) fn impact(&self) -> Option<ImpactSound> {
         match self.noises.len() {
             0 => None,
             nonzero => Some(self.noises[rand_usize(..nonzero)]),
@@ -118,6 +118,7 @@ fn play_roll(
     free_fall: Query<(&FreeFall, ChangeTrackers<FreeFall>), With<Klod>>,
     klod: Query<&Velocity, With<Klod>>,
     time: Res<Time>,
+    tuning: Res<KlodTuning>,
 ) {
     let delta = time.delta_seconds_f64();
     let current_time = time.seconds_since_startup();
@@ -133,7 +134,7 @@ fn play_roll(
     if let Ok(velocity) = klod.get_single() {
         let magnitude = velocity.linvel.length();
         if magnitude > 1.0 && !free_falling {
-            let volume = magnitude as f64 / MAX_KLOD_SPEED as f64;
+            let volume = magnitude as f64 / tuning.max_speed as f64;
             screen_print!(sec: 0.3, col: Color::RED, "strength: {volume:.3}, roll");
             audio_requests.send(AudioRequest::Roll(volume.min(1.0)));
         } else {
@@ -0,0 +1,354 @@
+//! Objects moving along a looping path of keyframes, such as doors,
+//! elevators and pendulums, pushing the klod on their way.
+//!
+//! Keyframes are offsets from the object's transform in the level file: their
+//! translation is added to the object's translation and their rotation
+//! applied on top of its rotation.
+use bevy::prelude::{Plugin as BevyPlugin, *};
+#[cfg(feature = "debug")]
+use bevy_inspector_egui::{Inspectable, RegisterInspectable};
+use serde::Deserialize;
+
+use crate::{state::GameState, system_helper::EasySystemSetCtor};
+
+/// How the object accelerates between two keyframes.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[cfg_attr(feature = "debug", derive(Inspectable))]
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub(crate) enum Easing {
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+impl Default for Easing {
+    fn default() -> Self {
+        Easing::Linear
+    }
+}
+impl Easing {
+    /// The eased progress, `progress` is between 0 and 1.
+    fn apply(self, progress: f32) -> f32 {
+        let s = progress;
+        match self {
+            Easing::Linear => s,
+            Easing::EaseIn => s * s,
+            Easing::EaseOut => 1.0 - (1.0 - s) * (1.0 - s),
+            Easing::EaseInOut => s * s * (3.0 - 2.0 * s),
+        }
+    }
+}
+
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[cfg_attr(feature = "debug", derive(Inspectable))]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub(crate) struct Keyframe {
+    pub(crate) translation: Vec3,
+    pub(crate) rotation: Quat,
+    /// Seconds to go from this keyframe to the next one, the last keyframe
+    /// goes back to the first.
+    pub(crate) duration: f32,
+    /// Easing of the motion toward the next keyframe.
+    #[serde(default)]
+    pub(crate) easing: Easing,
+}
+impl Default for Keyframe {
+    fn default() -> Self {
+        Keyframe {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            duration: 1.0,
+            easing: Easing::Linear,
+        }
+    }
+}
+
+/// An object following a looping path of [`Keyframe`]s.
+#[cfg_attr(feature = "debug", derive(Inspectable))]
+#[derive(Component, Debug, Clone)]
+pub(crate) struct Kinematic {
+    pub(crate) path: Vec<Keyframe>,
+    /// The transform keyframes are relative to, set from the object's
+    /// transform when it first moves.
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    pub(crate) origin: Option<Transform>,
+    #[cfg_attr(feature = "debug", inspectable(ignore))]
+    elapsed: f32,
+}
+impl Kinematic {
+    pub(crate) fn new(path: Vec<Keyframe>) -> Self {
+        Kinematic { path, origin: None, elapsed: 0.0 }
+    }
+    /// Offset from the origin `time` seconds after the start of the path.
+    fn sample(&self, time: f32) -> Option<(Vec3, Quat)> {
+        let total: f32 = self.path.iter().map(|k| k.duration.max(0.0)).sum();
+        if total <= 0.0 {
+            let first = self.path.first()?;
+            return Some((first.translation, first.rotation));
+        }
+        let mut time = time % total;
+        for (i, from) in self.path.iter().enumerate() {
+            let duration = from.duration;
+            if duration <= 0.0 {
+                continue;
+            }
+            if time > duration {
+                time -= duration;
+                continue;
+            }
+            let to = &self.path[(i + 1) % self.path.len()];
+            let progress = from.easing.apply(time / duration);
+            let translation = from.translation.lerp(to.translation, progress);
+            let rotation = from.rotation.slerp(to.rotation, progress);
+            return Some((translation, rotation));
+        }
+        None
+    }
+    /// The world transform at `time` when the path starts at `origin`.
+    fn transform_at(&self, origin: Transform, time: f32) -> Option<Transform> {
+        let (translation, rotation) = self.sample(time)?;
+        Some(Transform {
+            translation: origin.translation + translation,
+            rotation: rotation * origin.rotation,
+            scale: origin.scale,
+        })
+    }
+}
+
+fn follow_paths(time: Res<Time>, mut kinematics: Query<(&mut Kinematic, &mut Transform)>) {
+    for (mut kinematic, mut transform) in &mut kinematics {
+        let origin = *kinematic.origin.get_or_insert(*transform);
+        kinematic.elapsed += time.delta_seconds();
+        if let Some(new_transform) = kinematic.transform_at(origin, kinematic.elapsed) {
+            *transform = new_transform;
+        }
+    }
+}
+
+#[cfg(feature = "editor")]
+mod preview {
+    //! Lines showing the path of [`Kinematic`] objects in the editor.
+    //!
+    //! A ghost of each object loops along its path, so that its motion can be
+    //! checked without leaving the editor.
+    use bevy::{prelude::*, render::mesh::PrimitiveTopology, utils::HashMap};
+    use bevy_editor_pls_default_windows::hierarchy::{picking::IgnoreEditorRayCast, HideInEditor};
+    use bevy_mod_picking::Selection;
+
+    use super::Kinematic;
+    use crate::state::GameState;
+
+    /// Objects moved with the transform gizmo have a new origin.
+    pub(super) fn move_origins(
+        mut kinematics: Query<(&mut Kinematic, &Transform, &Selection), Changed<Transform>>,
+    ) {
+        for (mut kinematic, transform, selection) in &mut kinematics {
+            if selection.selected() {
+                kinematic.origin = Some(*transform);
+            }
+        }
+    }
+
+    /// Move objects back to the start of their path when the game is paused
+    /// for the editor, where the path is edited.
+    pub(super) fn back_to_origins(mut kinematics: Query<(&mut Kinematic, &mut Transform)>) {
+        for (mut kinematic, mut transform) in &mut kinematics {
+            kinematic.elapsed = 0.0;
+            if let Some(origin) = kinematic.origin {
+                *transform = origin;
+            }
+        }
+    }
+
+    /// Animate a ghost of kinematic objects along their path while the editor
+    /// is active.
+    pub(super) fn preview_motion(
+        mut cmds: Commands,
+        mut ghosts: Local<HashMap<Entity, Entity>>,
+        mut material: Local<Option<Handle<StandardMaterial>>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        mut transforms: Query<&mut Transform, Without<Kinematic>>,
+        kinematics: Query<(Entity, &Kinematic, &Transform, &Handle<Mesh>)>,
+        time: Res<Time>,
+        state: Res<State<GameState>>,
+    ) {
+        let is_active = *state.current() == GameState::Editor;
+        ghosts.retain(|object, ghost| {
+            let keep = is_active && kinematics.contains(*object);
+            if !keep {
+                cmds.entity(*ghost).despawn();
+            }
+            keep
+        });
+        if !is_active {
+            return;
+        }
+        let material = material.get_or_insert_with(|| {
+            materials.add(StandardMaterial {
+                base_color: Color::rgba(1.0, 1.0, 0.0, 0.3),
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            })
+        });
+        let time = time.seconds_since_startup() as f32;
+        for (entity, kinematic, transform, mesh) in &kinematics {
+            let origin = kinematic.origin.unwrap_or(*transform);
+            let ghost_transform = kinematic.transform_at(origin, time).unwrap_or(origin);
+            match ghosts.get(&entity) {
+                Some(ghost) => {
+                    if let Ok(mut transform) = transforms.get_mut(*ghost) {
+                        *transform = ghost_transform;
+                    }
+                }
+                None => {
+                    let ghost = cmds
+                        .spawn_bundle(PbrBundle {
+                            mesh: mesh.clone(),
+                            material: material.clone(),
+                            transform: ghost_transform,
+                            ..default()
+                        })
+                        .insert_bundle((
+                            Name::new("Motion preview"),
+                            HideInEditor,
+                            IgnoreEditorRayCast,
+                        ))
+                        .id();
+                    ghosts.insert(entity, ghost);
+                }
+            }
+        }
+    }
+
+    fn path_mesh(kinematic: &Kinematic, origin: &Transform) -> Mesh {
+        let mut positions: Vec<_> = kinematic
+            .path
+            .iter()
+            .map(|k| (origin.translation + k.translation).to_array())
+            .collect();
+        if let Some(&first) = positions.first() {
+            positions.push(first);
+        }
+        let len = positions.len();
+        let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; len]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; len]);
+        mesh
+    }
+
+    /// Show the path of kinematic objects while the editor is active.
+    pub(super) fn preview_paths(
+        mut cmds: Commands,
+        mut previews: Local<HashMap<Entity, Entity>>,
+        mut material: Local<Option<Handle<StandardMaterial>>>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        mut visibilities: Query<&mut Visibility>,
+        kinematics: Query<(Entity, &Kinematic, &Transform)>,
+        changed: Query<(), Or<(Changed<Kinematic>, Changed<Transform>)>>,
+        state: Res<State<GameState>>,
+    ) {
+        let is_visible = *state.current() == GameState::Editor;
+        previews.retain(|object, preview| {
+            let exists = kinematics.contains(*object);
+            if !exists {
+                cmds.entity(*preview).despawn();
+            }
+            exists
+        });
+        let material = material.get_or_insert_with(|| {
+            materials.add(StandardMaterial {
+                base_color: Color::YELLOW,
+                unlit: true,
+                ..default()
+            })
+        });
+        for (entity, kinematic, transform) in &kinematics {
+            let origin = kinematic.origin.as_ref().unwrap_or(transform);
+            match previews.get(&entity) {
+                Some(preview) if changed.contains(entity) && is_visible => {
+                    cmds.entity(*preview)
+                        .insert(meshes.add(path_mesh(kinematic, origin)));
+                }
+                Some(_) => {}
+                None => {
+                    let preview = cmds
+                        .spawn_bundle(PbrBundle {
+                            mesh: meshes.add(path_mesh(kinematic, origin)),
+                            material: material.clone(),
+                            visibility: Visibility { is_visible },
+                            ..default()
+                        })
+                        .insert_bundle((
+                            Name::new("Path preview"),
+                            HideInEditor,
+                            IgnoreEditorRayCast,
+                        ))
+                        .id();
+                    previews.insert(entity, preview);
+                }
+            }
+        }
+        for preview in previews.values() {
+            if let Ok(mut visibility) = visibilities.get_mut(*preview) {
+                if visibility.is_visible != is_visible {
+                    visibility.is_visible = is_visible;
+                }
+            }
+        }
+    }
+}
+
+pub(crate) struct Plugin;
+impl BevyPlugin for Plugin {
+    fn build(&self, app: &mut App) {
+        #[cfg(feature = "debug")]
+        app.register_inspectable::<Kinematic>();
+
+        #[cfg(feature = "editor")]
+        app.add_system_set(GameState::Editor.on_enter(preview::back_to_origins))
+            .add_system_set(GameState::Editor.on_update(preview::move_origins))
+            .add_system(preview::preview_paths)
+            .add_system(preview::preview_motion);
+
+        app.add_system_set(GameState::Playing.on_update(follow_paths));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(x: f32, duration: f32) -> Keyframe {
+        Keyframe { translation: Vec3::X * x, duration, ..default() }
+    }
+
+    #[test]
+    fn sample_interpolates_and_loops() {
+        let kinematic = Kinematic::new(vec![keyframe(0.0, 1.0), keyframe(4.0, 1.0)]);
+        let at = |time| kinematic.sample(time).unwrap().0.x;
+        assert_eq!(at(0.0), 0.0);
+        assert_eq!(at(0.5), 2.0);
+        assert_eq!(at(1.0), 4.0);
+        assert_eq!(at(1.5), 2.0);
+        assert_eq!(at(2.5), 2.0);
+    }
+
+    #[test]
+    fn sample_skips_zero_durations() {
+        let path = vec![keyframe(0.0, 1.0), keyframe(2.0, 0.0), keyframe(4.0, 1.0)];
+        let kinematic = Kinematic::new(path);
+        let (translation, _) = kinematic.sample(1.25).unwrap();
+        assert!(translation.is_finite());
+        assert_eq!(translation.x, 3.0);
+    }
+
+    #[test]
+    fn sample_of_still_paths() {
+        let kinematic = Kinematic::new(vec![keyframe(3.0, 0.0)]);
+        assert_eq!(kinematic.sample(1.0).unwrap().0.x, 3.0);
+        assert_eq!(Kinematic::new(Vec::new()).sample(1.0), None);
+    }
+}
@@ -0,0 +1,140 @@
+//! The Bone Klod game, added to an app by the `klod` binary.
+//!
+//! The `klod-lint` and `klod-convert` binaries use [`lint_level`] and
+//! `convert_level` to check and convert level files.
+mod animate;
+mod audio;
+mod ball;
+#[cfg(feature = "editor")]
+mod box_scene;
+mod cam;
+mod campaign;
+mod checkpoint;
+#[cfg(feature = "editor")]
+mod editor;
+mod force;
+mod game_audio;
+mod kinematic;
+mod pickup;
+mod powers;
+mod prefabs;
+mod rules;
+mod scene;
+mod score;
+mod state;
+mod system_helper;
+mod trigger;
+mod ui;
+
+#[cfg(feature = "editor")]
+use std::env;
+
+use bevy::prelude::{Plugin as BevyPlugin, *};
+use bevy_debug_text_overlay::screen_print;
+use scene::KlodScene;
+use state::GameState;
+
+#[cfg(feature = "editor")]
+pub use scene::convert_level;
+pub use scene::lint::{lint_level, LintIssue};
+
+/// Event to trigger a game over.
+#[derive(Debug)]
+pub struct GameOver(pub EndReason);
+
+/// What triggered the game over.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EndReason {
+    Victory,
+    Loss,
+    /// The klod entered a kill zone.
+    KillZone,
+}
+
+#[derive(Component, Clone)]
+struct WaitRoot;
+
+/// The game, added after bevy's `DefaultPlugins` and the rapier physics plugin.
+pub struct Plugin;
+impl BevyPlugin for Plugin {
+    fn build(&self, app: &mut App) {
+        let initial_state = if cfg!(feature = "editor") {
+            GameState::Playing
+        } else {
+            GameState::MainMenu
+        };
+        app.add_state(initial_state);
+
+        #[cfg(feature = "editor")]
+        app.add_plugin(bevy_scene_hook::HookPlugin)
+            .add_plugin(editor::Plugin);
+
+        #[cfg(feature = "editor")]
+        if env::args().nth(1).as_deref() == Some("--load-greybox") {
+            app.add_startup_system(box_scene::load_box_level)
+                .add_system(box_scene::save_box_level.exclusive_system().at_start());
+        }
+        app.init_resource::<LightSwitch>()
+            .add_plugin(bevy_debug_text_overlay::OverlayPlugin { font_size: 24.0, ..default() })
+            .add_plugin(scene::Plugin)
+            .add_plugin(animate::Plugin)
+            .add_plugin(powers::Plugin)
+            .add_plugin(score::Plugin)
+            .add_plugin(campaign::Plugin)
+            .add_plugin(checkpoint::Plugin)
+            .add_plugin(kinematic::Plugin)
+            .add_plugin(pickup::Plugin)
+            .add_plugin(force::Plugin)
+            .add_plugin(trigger::Plugin)
+            .add_plugin(rules::Plugin)
+            .add_plugin(audio::Plugin)
+            .add_plugin(game_audio::Plugin)
+            .add_plugin(cam::Plugin)
+            .add_plugin(ball::Plugin)
+            .add_plugin(ui::Plugin)
+            .add_event::<GameOver>()
+            .add_startup_system(|| {
+                screen_print!(sec: 10_000_000_000.0, "");
+            })
+            .add_startup_system(setup.exclusive_system().at_start());
+    }
+}
+
+pub(crate) fn cleanup_marked<T: Component>(mut cmds: Commands, query: Query<Entity, With<T>>) {
+    screen_print!(sec: 3.0, "Cleaned up Something (can't show)");
+    for entity in query.iter() {
+        cmds.entity(entity).despawn_recursive();
+    }
+}
+
+fn setup(world: &mut World) {
+    KlodScene::load(world, scene::level_file("default"));
+}
+
+pub(crate) struct UsesGamepad {
+    pub(crate) yes: bool,
+}
+pub(crate) struct LightSwitch {
+    pub(crate) on: bool,
+}
+impl Default for UsesGamepad {
+    fn default() -> Self {
+        Self { yes: true }
+    }
+}
+impl Default for LightSwitch {
+    fn default() -> Self {
+        Self { on: true }
+    }
+}
+
+pub(crate) mod collision_groups {
+    use bevy_rapier3d::prelude::CollisionGroups;
+
+    pub(crate) const KLOD: CollisionGroups = CollisionGroups::new(0b00000001, 0b110110);
+    pub(crate) const AGGLO: CollisionGroups = CollisionGroups::new(0b0000010, 0b011011);
+    pub(crate) const SEEAG: CollisionGroups = CollisionGroups::new(0b0000010, 0b000011);
+    pub(crate) const MUSIC: CollisionGroups = CollisionGroups::new(0b0000100, 0b000001);
+    pub(crate) const CAM: CollisionGroups = CollisionGroups::new(0b000001000, 0b000011);
+    pub(crate) const FORCE: CollisionGroups = CollisionGroups::new(0b0010000, 0b000011);
+}
@@ -0,0 +1,60 @@
+//! Collectibles the klod picks up by rolling through them.
+//!
+//! Time pickups, hourglasses adding seconds to the timer, are the only
+//! collectibles for now.
+use bevy::prelude::{Plugin as BevyPlugin, *};
+use bevy_debug_text_overlay::screen_print;
+use bevy_rapier3d::prelude::RapierContext;
+
+use crate::{
+    audio::{AudioAssets, AudioRequest, AudioRequestSystem, ImpactSound},
+    ball::KlodBall,
+    game_audio::NoiseOnHit,
+    score::GameData,
+    state::GameState,
+    system_helper::EasySystemSetCtor,
+};
+
+/// Adds `seconds` to the timer when the klod touches it.
+#[derive(Component, Clone, Copy, Debug)]
+pub(crate) struct TimePickup {
+    pub(crate) seconds: f32,
+}
+
+fn collect_time_pickups(
+    mut cmds: Commands,
+    mut timer: ResMut<GameData>,
+    mut audio_requests: EventWriter<AudioRequest>,
+    audio: Res<AudioAssets>,
+    pickups: Query<(Entity, &TimePickup, Option<&NoiseOnHit>)>,
+    klods_query: Query<Entity, With<KlodBall>>,
+    rapier_context: Res<RapierContext>,
+    mut klods: Local<Vec<Entity>>,
+) {
+    klods.extend(&klods_query);
+    for (entity, pickup, noise) in &pickups {
+        let not_pickup = |e1, e2| (e1 == entity).then(|| e2).unwrap_or(e1);
+        let touched = rapier_context
+            .intersections_with(entity)
+            .any(|(e1, e2, colliding)| colliding && klods.contains(&not_pickup(e1, e2)));
+        if touched {
+            screen_print!(sec: 3.0, "Picked up {} seconds", pickup.seconds);
+            timer.add_time(pickup.seconds);
+            let sound = noise
+                .and_then(NoiseOnHit::impact)
+                .unwrap_or(ImpactSound::Bell);
+            audio_requests.send(AudioRequest::PlayEffect(audio.impact(sound), 0.8));
+            cmds.entity(entity).despawn_recursive();
+        }
+    }
+    klods.clear();
+}
+
+pub(crate) struct Plugin;
+impl BevyPlugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            GameState::Playing.on_update(collect_time_pickups.before(AudioRequestSystem)),
+        );
+    }
+}
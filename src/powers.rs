@@ -10,7 +10,7 @@ use bevy_inspector_egui::{Inspectable, RegisterInspectable};
 use bevy_rapier3d::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::ball::KlodElem;
+use crate::{ball::KlodElem, rules::LevelEvent};
 
 #[cfg_attr(feature = "debug", derive(Inspectable))]
 #[derive(Component, Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -45,8 +45,9 @@ pub(crate) struct ElementalObstacle {
 
 fn break_elemental_obstacle(
     kloded: Query<(&Power, Entity, &KlodElem)>,
-    obstacles: Query<&ElementalObstacle>,
+    obstacles: Query<(&ElementalObstacle, Option<&Name>)>,
     mut collisions: EventReader<ContactForceEvent>,
+    mut level_events: EventWriter<LevelEvent>,
     mut cmds: Commands,
 ) {
     for ContactForceEvent { collider1, collider2, .. } in collisions.iter() {
@@ -55,7 +56,7 @@ fn break_elemental_obstacle(
             (_, true) => *collider1,
             _ => continue,
         };
-        if let Ok(obstacle) = obstacles.get(obstacle_entity) {
+        if let Ok((obstacle, name)) = obstacles.get(obstacle_entity) {
             let kloded: HashMap<_, _> = kloded
                 .iter()
                 .map(|(power, entity, elem)| (*power, (entity, elem.scene)))
@@ -80,6 +81,9 @@ fn break_elemental_obstacle(
                     }
                 }
                 cmds.entity(obstacle_entity).despawn_recursive();
+                if let Some(name) = name {
+                    level_events.send(LevelEvent::ObstacleDestroyed(name.to_string()));
+                }
             }
         }
     }
@@ -1,23 +1,32 @@
+mod collider_mesh;
+
 use std::ops::{Div, Mul};
 
 #[cfg(feature = "editor")]
 use bevy::ui::FocusPolicy;
 use bevy::{
-    ecs::query::{QueryItem, WorldQuery},
+    asset::AssetPath,
+    ecs::query::{AnyOf, QueryItem, WorldQuery},
     ecs::system::EntityCommands,
     prelude::*,
 };
 #[cfg(feature = "debug")]
 use bevy_inspector_egui::Inspectable;
-use bevy_rapier3d::prelude::*;
-use serde::Deserialize;
+use bevy_rapier3d::{prelude::*, utils};
+use serde::{de::Error as _, Deserialize, Deserializer};
 
 use crate::{
     ball::Agglomerable,
+    checkpoint::{Checkpoint, KillZone},
     collision_groups as groups,
+    force::{ForceKind, ForceVolume},
     game_audio::MusicTrigger,
+    kinematic::{Keyframe, Kinematic},
+    pickup::TimePickup,
     powers::{ElementalObstacle, Power},
+    trigger::{TriggerAction, TriggerWhen, TriggerZone},
 };
+pub(crate) use collider_mesh::{baked_collider_mesh, collider_mesh};
 
 pub(crate) trait Prefab {
     type Query: WorldQuery;
@@ -63,7 +72,7 @@ impl From<SerdeTransform> for Transform {
 }
 
 #[cfg_attr(feature = "editor", derive(serde::Serialize))]
-#[derive(Deserialize, Debug, Clone)]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
 pub(crate) enum SerdeCollider {
     Ball {
         radius: f32,
@@ -98,6 +107,42 @@ pub(crate) enum SerdeCollider {
         radius: f32,
         border_radius: f32,
     },
+    /// The convex hull of `points`.
+    ConvexHull {
+        points: Vec<Vec3>,
+    },
+    /// The triangles of a mesh in a glTF file, such as `"crypt.glb#Mesh0/Primitive0"`.
+    ///
+    /// The mesh is loaded asynchronously, entities with this collider only
+    /// get a [`Collider`] once `crate::scene` loaded it.
+    TriMesh {
+        mesh: AssetPath<'static>,
+        scale: Vec3,
+    },
+    /// Several shapes, each with its own transform relative to the collider.
+    ///
+    /// The shapes can't be [`SerdeCollider::TriMesh`], only meshes of whole
+    /// colliders are loaded.
+    Compound {
+        shapes: Vec<CompoundShape>,
+    },
+}
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) struct CompoundShape {
+    translation: Vec3,
+    rotation: Quat,
+    #[serde(deserialize_with = "no_trimesh")]
+    shape: SerdeCollider,
+}
+/// Deserialize a [`SerdeCollider`], failing on [`SerdeCollider::TriMesh`].
+fn no_trimesh<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SerdeCollider, D::Error> {
+    match SerdeCollider::deserialize(deserializer)? {
+        SerdeCollider::TriMesh { mesh, .. } => Err(D::Error::custom(format!(
+            "TriMesh {mesh:?} in a Compound collider, only whole colliders can be a TriMesh"
+        ))),
+        shape => Ok(shape),
+    }
 }
 impl Div<Vec3> for SerdeCollider {
     type Output = SerdeCollider;
@@ -107,86 +152,130 @@ impl Div<Vec3> for SerdeCollider {
         self * rhs
     }
 }
+/// Whether `a` and `b` are equal, with some tolerance for rounding errors.
+fn same_scale(a: f32, b: f32) -> bool {
+    (a - b).abs() <= 1e-4 * a.abs().max(b.abs())
+}
+/// Whether `scale` is the same in all directions.
+pub(crate) fn is_uniform(scale: Vec3) -> bool {
+    same_scale(scale.x, scale.y) && same_scale(scale.y, scale.z)
+}
+/// How much the radius of a capsule along `axis` scales when scaled by `scale`,
+/// `None` if the scale isn't the same in all directions perpendicular to `axis`.
+fn capsule_radius_scale(axis: Vec3, scale: Vec3) -> Option<f32> {
+    let Vec3 { x, y, z } = scale;
+    let along = |v: f32| v.abs() > axis.length() * (1.0 - 1e-4);
+    match () {
+        () if same_scale(x, y) && same_scale(y, z) => Some(x),
+        () if along(axis.x) && same_scale(y, z) => Some(y),
+        () if along(axis.y) && same_scale(x, z) => Some(x),
+        () if along(axis.z) && same_scale(x, y) => Some(x),
+        () => None,
+    }
+}
 impl Mul<Vec3> for SerdeCollider {
     type Output = SerdeCollider;
+
+    /// Bake the scale `rhs` into this collider.
+    ///
+    /// Shapes that can't be stretched along `rhs` become the convex hull of
+    /// their scaled surface. Rounded borders stay round, but the outer extents
+    /// of the shape are scaled.
     fn mul(self, rhs: Vec3) -> Self::Output {
         use SerdeCollider::*;
-        let Vec3 { x, y, z } = rhs;
-        let avg_mul = (x + y + z) / 3.0;
-        match self {
-            SerdeCollider::Ball { radius } => Ball { radius: radius * avg_mul },
-            SerdeCollider::Cuboid { half_extents } => Cuboid { half_extents: half_extents * rhs },
-            SerdeCollider::Capsule { a, b, radius } => {
-                Capsule { a: a * rhs, b: b * rhs, radius: radius * avg_mul }
+        let abs = rhs.abs();
+        let Vec3 { x, y, z } = abs;
+        let round_xz = same_scale(x, z);
+        let hull = |collider: SerdeCollider| {
+            let collider = collider.to_collider();
+            let points = collider.map_or_else(Vec::new, |c| collider_mesh::hull_points(&c));
+            ConvexHull {
+                points: points.into_iter().map(|p| p * rhs).collect(),
             }
-            SerdeCollider::Cylinder { half_height, radius } => Cylinder {
-                half_height: half_height * avg_mul,
-                radius: radius * avg_mul,
-            },
-            SerdeCollider::Cone { half_height, radius } => Cone {
-                half_height: half_height * avg_mul,
-                radius: radius * avg_mul,
-            },
-            SerdeCollider::RoundCuboid { half_extents, border_radius } => RoundCuboid {
-                half_extents: half_extents * rhs,
-                border_radius: border_radius * avg_mul,
+        };
+        match self {
+            Ball { radius } if same_scale(x, y) && round_xz => Ball { radius: radius * x },
+            Cuboid { half_extents } => Cuboid { half_extents: half_extents * abs },
+            Capsule { a, b, radius } => match capsule_radius_scale(b - a, abs) {
+                Some(scale) => Capsule { a: a * rhs, b: b * rhs, radius: radius * scale },
+                None => hull(Capsule { a, b, radius }),
             },
-            SerdeCollider::RoundCylinder { half_height, radius, border_radius } => RoundCylinder {
-                half_height: half_height * avg_mul,
-                radius: radius * avg_mul,
-                border_radius: border_radius * avg_mul,
+            Cylinder { half_height, radius } if round_xz => {
+                Cylinder { half_height: half_height * y, radius: radius * x }
+            }
+            Cone { half_height, radius } if round_xz => {
+                Cone { half_height: half_height * y, radius: radius * x }
+            }
+            RoundCuboid { half_extents, border_radius } => {
+                let border = border_radius * abs.min_element();
+                RoundCuboid {
+                    half_extents: (half_extents + border_radius) * abs - border,
+                    border_radius: border,
+                }
+            }
+            RoundCylinder { half_height, radius, border_radius } if round_xz => {
+                let border = border_radius * x.min(y);
+                RoundCylinder {
+                    half_height: (half_height + border_radius) * y - border,
+                    radius: (radius + border_radius) * x - border,
+                    border_radius: border,
+                }
+            }
+            RoundCone { half_height, radius, border_radius } if round_xz => {
+                let border = border_radius * x.min(y);
+                RoundCone {
+                    half_height: (half_height + border_radius) * y - border,
+                    radius: (radius + border_radius) * x - border,
+                    border_radius: border,
+                }
+            }
+            ConvexHull { points } => ConvexHull {
+                points: points.into_iter().map(|p| p * rhs).collect(),
             },
-            SerdeCollider::RoundCone { half_height, radius, border_radius } => RoundCone {
-                half_height: half_height * avg_mul,
-                radius: radius * avg_mul,
-                border_radius: border_radius * avg_mul,
+            TriMesh { mesh, scale } => TriMesh { mesh, scale: scale * rhs },
+            Compound { shapes } => Compound {
+                shapes: shapes.into_iter().map(|shape| shape.scaled(rhs)).collect(),
             },
+            // Balls, cylinders and cones with a different scale on the x and z axes.
+            other => hull(other),
+        }
+    }
+}
+impl CompoundShape {
+    fn scaled(self, scale: Vec3) -> Self {
+        let CompoundShape { translation, rotation, shape } = self;
+        let translation = translation * scale;
+        let local = Mat3::from_quat(rotation);
+        let aligned = [local.x_axis, local.y_axis, local.z_axis]
+            .iter()
+            .all(|axis| axis.abs().max_element() > 1.0 - 1e-4);
+        let convex = !matches!(shape, SerdeCollider::Compound { .. });
+        if aligned || !convex {
+            // Only an approximation for rotated non-convex shapes.
+            let local_scale = (rotation.inverse() * scale).abs();
+            CompoundShape { translation, rotation, shape: shape * local_scale }
+        } else {
+            // Stretching a rotated shape shears it, only convex hulls can be sheared.
+            let shape = shape.to_collider();
+            let points = shape.map_or_else(Vec::new, |c| collider_mesh::hull_points(&c));
+            let points = points.into_iter().map(|p| rotation * p * scale).collect();
+            CompoundShape {
+                translation,
+                rotation: Quat::IDENTITY,
+                shape: SerdeCollider::ConvexHull { points },
+            }
         }
     }
 }
 impl From<SerdeCollider> for Mesh {
     fn from(collider: SerdeCollider) -> Self {
         match collider {
-            SerdeCollider::Ball { radius } => shape::Icosphere { radius, subdivisions: 3 }.into(),
-            SerdeCollider::Cuboid { half_extents: Vec3 { x, y, z } } => {
-                shape::Box::new(x * 2.0, y * 2.0, z * 2.0).into()
-            }
-            SerdeCollider::Capsule { a, b, radius } => shape::Capsule {
-                radius,
-                depth: a.distance(b),
-                latitudes: 8,
-                rings: 0,
-                longitudes: 8,
-                uv_profile: default(),
-            }
-            .into(),
-            SerdeCollider::Cylinder { half_height, radius } => {
-                shape::Box::new(radius * 2.0, half_height * 2.0, radius * 2.0).into()
-            }
-            SerdeCollider::Cone { half_height, radius } => {
-                shape::Box::new(radius * 2.0, half_height * 2.0, radius * 2.0).into()
-            }
-
-            SerdeCollider::RoundCuboid { half_extents: Vec3 { x, y, z }, border_radius } => {
-                shape::Box::new(
-                    (border_radius + x) * 2.0,
-                    (border_radius + y) * 2.0,
-                    (border_radius + z) * 2.0,
-                )
-                .into()
-            }
-            SerdeCollider::RoundCylinder { half_height, radius, border_radius } => shape::Box::new(
-                (border_radius + radius) * 2.0,
-                (border_radius + half_height) * 2.0,
-                (border_radius + radius) * 2.0,
-            )
-            .into(),
-            SerdeCollider::RoundCone { half_height, radius, border_radius } => shape::Box::new(
-                (border_radius + radius) * 2.0,
-                (border_radius + half_height) * 2.0,
-                (border_radius + radius) * 2.0,
-            )
-            .into(),
+            // Only known once the mesh is loaded, see `fit_pickbox_to_collider`.
+            SerdeCollider::TriMesh { .. } => collider_mesh::flat_mesh([]),
+            collider => match collider.to_collider() {
+                Some(collider) => collider_mesh(&collider),
+                None => collider_mesh::flat_mesh([]),
+            },
         }
     }
 }
@@ -232,6 +321,28 @@ impl<'a> From<&'a Collider> for SerdeCollider {
                 radius: view.inner_shape().radius(),
                 border_radius: view.border_radius(),
             },
+            ColliderView::ConvexPolyhedron(view) => {
+                let points = view.raw.points().iter().map(|p| Vec3::new(p.x, p.y, p.z));
+                SerdeCollider::ConvexHull { points: points.collect() }
+            }
+            // The mesh asset of a triangle mesh is unknown here, entities
+            // loaded with one keep it in their `LevelCollider`.
+            ColliderView::TriMesh(view) => {
+                warn!("Saving a triangle mesh collider without its mesh as a convex hull");
+                let points = view.raw.vertices().iter().map(|p| Vec3::new(p.x, p.y, p.z));
+                SerdeCollider::ConvexHull { points: points.collect() }
+            }
+            ColliderView::Compound(view) => {
+                let shapes = view.raw.shapes().iter().map(|(isometry, shape)| {
+                    let transform = utils::iso_to_transform(isometry, 1.0);
+                    CompoundShape {
+                        translation: transform.translation,
+                        rotation: transform.rotation,
+                        shape: SerdeCollider::from(&Collider::from(shape.clone())),
+                    }
+                });
+                SerdeCollider::Compound { shapes: shapes.collect() }
+            }
             _ => {
                 let aabb = collider.raw.compute_local_aabb();
                 SerdeCollider::Cuboid { half_extents: aabb.half_extents().into() }
@@ -239,33 +350,90 @@ impl<'a> From<&'a Collider> for SerdeCollider {
         }
     }
 }
-impl From<SerdeCollider> for Collider {
-    fn from(data: SerdeCollider) -> Self {
-        match data {
-            SerdeCollider::Ball { radius } => Collider::ball(radius),
-            SerdeCollider::Cuboid { half_extents: Vec3 { x, y, z } } => Collider::cuboid(x, y, z),
-            SerdeCollider::Capsule { a, b, radius } => Collider::capsule(a, b, radius),
+impl SerdeCollider {
+    /// The physics collider of this shape.
+    ///
+    /// `None` for a [`SerdeCollider::TriMesh`], which collider is only known
+    /// once its mesh is loaded, and for shapes without volume.
+    pub(crate) fn to_collider(&self) -> Option<Collider> {
+        let collider = match self {
+            SerdeCollider::Ball { radius } => Collider::ball(*radius),
+            SerdeCollider::Cuboid { half_extents: Vec3 { x, y, z } } => {
+                Collider::cuboid(*x, *y, *z)
+            }
+            SerdeCollider::Capsule { a, b, radius } => Collider::capsule(*a, *b, *radius),
             SerdeCollider::Cylinder { half_height, radius } => {
-                Collider::cylinder(half_height, radius)
+                Collider::cylinder(*half_height, *radius)
             }
-            SerdeCollider::Cone { half_height, radius } => Collider::cone(half_height, radius),
+            SerdeCollider::Cone { half_height, radius } => Collider::cone(*half_height, *radius),
             SerdeCollider::RoundCuboid { half_extents: Vec3 { x, y, z }, border_radius } => {
-                Collider::round_cuboid(x, y, z, border_radius)
+                Collider::round_cuboid(*x, *y, *z, *border_radius)
             }
             SerdeCollider::RoundCylinder { half_height, radius, border_radius } => {
-                Collider::round_cylinder(half_height, radius, border_radius)
+                Collider::round_cylinder(*half_height, *radius, *border_radius)
             }
             SerdeCollider::RoundCone { half_height, radius, border_radius } => {
-                Collider::round_cone(half_height, radius, border_radius)
+                Collider::round_cone(*half_height, *radius, *border_radius)
             }
-        }
+            SerdeCollider::ConvexHull { points } => match Collider::convex_hull(points) {
+                Some(collider) => collider,
+                None => {
+                    // Less than 4 points or all points on a plane.
+                    warn!("Ignoring a convex hull collider without volume: {points:?}");
+                    return None;
+                }
+            },
+            SerdeCollider::TriMesh { .. } => return None,
+            SerdeCollider::Compound { shapes } => {
+                let shapes: Vec<_> = shapes
+                    .iter()
+                    .filter_map(|CompoundShape { translation, rotation, shape }| {
+                        Some((*translation, *rotation, shape.to_collider()?))
+                    })
+                    .collect();
+                if shapes.is_empty() {
+                    warn!("Ignoring a compound collider without shapes");
+                    return None;
+                }
+                Collider::compound(shapes)
+            }
+        };
+        Some(collider)
+    }
+}
+
+/// The collider of an entity as written in the level file, when it can't be
+/// recovered from its [`Collider`]: a [`SerdeCollider::TriMesh`], a shape
+/// without volume, or a collider with a non-uniform scale baked in.
+#[derive(Component)]
+pub(crate) struct LevelCollider(pub(crate) SerdeCollider);
+
+/// Query of the collider to write in the level file, see [`saved_collider`].
+pub(crate) type SavedColliderQuery = AnyOf<(&'static Collider, &'static LevelCollider)>;
+
+/// The collider to write in the level file of an entity.
+pub(crate) fn saved_collider(item: QueryItem<SavedColliderQuery>) -> SerdeCollider {
+    match item {
+        (_, Some(LevelCollider(collider))) => collider.clone(),
+        (Some(collider), None) => collider.into(),
+        // `AnyOf` only matches entities with at least one of the two.
+        (None, None) => unreachable!(),
     }
 }
 
+/// Insert the [`Collider`] of `collider`, or a [`LevelCollider`] when it has
+/// none yet, so that it is still saved with the level.
+pub(crate) fn insert_collider(cmds: &mut EntityCommands, collider: SerdeCollider) {
+    match collider.to_collider() {
+        Some(physics_collider) => cmds.insert(physics_collider),
+        None => cmds.insert(LevelCollider(collider)),
+    };
+}
+
 /// Static physic objects
 #[cfg_attr(feature = "editor", derive(serde::Serialize))]
 #[cfg_attr(feature = "debug", derive(Inspectable))]
-#[derive(Debug, Deserialize, Component, Clone)]
+#[derive(Debug, Deserialize, Component, Clone, PartialEq)]
 pub(crate) struct Scenery {
     pub(crate) weakness: Vec<Power>,
 }
@@ -286,11 +454,50 @@ impl Prefab for Scenery {
     }
 }
 
+/// Objects following a looping path, see [`crate::kinematic`].
 #[cfg_attr(feature = "editor", derive(serde::Serialize))]
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub(crate) struct KinematicData {
+    pub(crate) path: Vec<Keyframe>,
+}
+impl Prefab for KinematicData {
+    type Query = &'static Kinematic;
+
+    fn from_query(kinematic: QueryItem<Self::Query>) -> Self {
+        KinematicData { path: kinematic.path.clone() }
+    }
+    fn spawn(self, cmds: &mut EntityCommands) {
+        cmds.insert_bundle((RigidBody::KinematicPositionBased, Kinematic::new(self.path)));
+    }
+}
+
+/// Hourglasses adding time to the timer, see [`crate::pickup`].
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub(crate) struct TimePickupData {
+    pub(crate) seconds: f32,
+}
+impl Prefab for TimePickupData {
+    type Query = &'static TimePickup;
+
+    fn from_query(pickup: QueryItem<Self::Query>) -> Self {
+        TimePickupData { seconds: pickup.seconds }
+    }
+    fn spawn(self, cmds: &mut EntityCommands) {
+        cmds.insert_bundle((
+            RigidBody::Fixed,
+            Sensor,
+            groups::MUSIC,
+            TimePickup { seconds: self.seconds },
+        ));
+    }
+}
+
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub(crate) struct AggloData {
     mass: f32,
-    power: Power,
+    pub(crate) power: Power,
 }
 impl AggloData {
     pub(crate) fn new(mass: f32, power: Power) -> Self {
@@ -306,6 +513,8 @@ pub(crate) struct AggloBundle {
     contact_threshold: ContactForceEventThreshold,
     collision_group: CollisionGroups,
     power: Power,
+    impulse: ExternalImpulse,
+    velocity: Velocity,
 }
 impl AggloBundle {
     pub(crate) fn new(mass: f32, power: Power) -> Self {
@@ -317,6 +526,8 @@ impl AggloBundle {
             mass: ColliderMassProperties::Mass(mass),
             rigid_body: RigidBody::Dynamic,
             collision_group: groups::AGGLO,
+            impulse: ExternalImpulse::default(),
+            velocity: Velocity::default(),
         }
     }
 }
@@ -334,59 +545,181 @@ impl Prefab for AggloData {
     }
 }
 
+/// Query of a sensor volume prefab with the `T` component, see [`spawn_sensor`].
+type SensorQuery<T> = (
+    &'static T,
+    SavedColliderQuery,
+    &'static Transform,
+    &'static Name,
+);
+
+/// Spawn a sensor volume in the `group` collision group, `component` being
+/// what it does. In the editor, the volume is also visible and selectable.
+fn spawn_sensor(
+    cmds: &mut EntityCommands,
+    component: impl Component,
+    group: CollisionGroups,
+    name: String,
+    collider: SerdeCollider,
+    transform: SerdeTransform,
+) {
+    cmds.insert_bundle((
+        Name::new(name),
+        component,
+        Sensor,
+        group,
+        Transform::from(transform),
+        GlobalTransform::default(),
+    ));
+    insert_collider(cmds, collider);
+    #[cfg(feature = "editor")]
+    cmds.insert_bundle((
+        Visibility::default(),
+        ComputedVisibility::default(),
+        bevy_mod_picking::PickableMesh::default(),
+        Interaction::default(),
+        FocusPolicy::default(),
+        bevy_mod_picking::Selection::default(),
+        bevy_transform_gizmo::GizmoTransformable,
+    ));
+}
+
 #[cfg_attr(feature = "editor", derive(serde::Serialize))]
 #[derive(Debug, Deserialize, Clone)]
 pub(crate) struct MusicTriggerData {
-    name: String,
+    pub(crate) name: String,
     trigger: MusicTrigger,
     pub(crate) collider: SerdeCollider,
-    transform: SerdeTransform,
+    pub(crate) transform: SerdeTransform,
 }
 impl MusicTriggerData {
-    pub(crate) fn new(name: String, trigger: MusicTrigger, collider: &Collider) -> Self {
-        Self {
-            name,
-            trigger,
-            collider: collider.into(),
-            transform: default(),
-        }
+    pub(crate) fn new(name: String, trigger: MusicTrigger, collider: SerdeCollider) -> Self {
+        Self { name, trigger, collider, transform: default() }
     }
 }
 impl Prefab for MusicTriggerData {
-    type Query = (
-        &'static MusicTrigger,
-        &'static Collider,
-        &'static Transform,
-        &'static Name,
-    );
+    type Query = SensorQuery<MusicTrigger>;
 
     fn from_query((trigger, collider, transform, name): QueryItem<Self::Query>) -> Self {
         MusicTriggerData {
             name: name.to_string(),
             trigger: *trigger,
-            collider: collider.into(),
+            collider: saved_collider(collider),
             transform: (*transform).into(),
         }
     }
     fn spawn(self, cmds: &mut EntityCommands) {
-        cmds.insert_bundle((
-            Name::new(self.name),
-            self.trigger,
-            Sensor,
-            groups::MUSIC,
-            Transform::from(self.transform),
-            GlobalTransform::default(),
-            Collider::from(self.collider),
-        ));
-        #[cfg(feature = "editor")]
-        cmds.insert_bundle((
-            Visibility::default(),
-            ComputedVisibility::default(),
-            bevy_mod_picking::PickableMesh::default(),
-            Interaction::default(),
-            FocusPolicy::default(),
-            bevy_mod_picking::Selection::default(),
-            bevy_transform_gizmo::GizmoTransformable,
-        ));
+        let Self { name, trigger, collider, transform } = self;
+        spawn_sensor(cmds, trigger, groups::MUSIC, name, collider, transform);
+    }
+}
+
+/// A [`Checkpoint`] volume, the klod respawns at its center.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct CheckpointData {
+    pub(crate) name: String,
+    pub(crate) collider: SerdeCollider,
+    pub(crate) transform: SerdeTransform,
+}
+impl Prefab for CheckpointData {
+    type Query = SensorQuery<Checkpoint>;
+
+    fn from_query((_, collider, transform, name): QueryItem<Self::Query>) -> Self {
+        CheckpointData {
+            name: name.to_string(),
+            collider: saved_collider(collider),
+            transform: (*transform).into(),
+        }
+    }
+    fn spawn(self, cmds: &mut EntityCommands) {
+        let Self { name, collider, transform } = self;
+        spawn_sensor(cmds, Checkpoint, groups::MUSIC, name, collider, transform);
+    }
+}
+
+/// A [`KillZone`] volume, see [`crate::checkpoint::RespawnRules`] for what it does.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct KillZoneData {
+    pub(crate) name: String,
+    pub(crate) collider: SerdeCollider,
+    pub(crate) transform: SerdeTransform,
+}
+impl Prefab for KillZoneData {
+    type Query = SensorQuery<KillZone>;
+
+    fn from_query((_, collider, transform, name): QueryItem<Self::Query>) -> Self {
+        KillZoneData {
+            name: name.to_string(),
+            collider: saved_collider(collider),
+            transform: (*transform).into(),
+        }
+    }
+    fn spawn(self, cmds: &mut EntityCommands) {
+        let Self { name, collider, transform } = self;
+        spawn_sensor(cmds, KillZone, groups::MUSIC, name, collider, transform);
+    }
+}
+
+/// A [`TriggerZone`] volume, running its `actions` when the klod goes through it.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct TriggerZoneData {
+    pub(crate) name: String,
+    pub(crate) when: TriggerWhen,
+    pub(crate) actions: Vec<TriggerAction>,
+    pub(crate) collider: SerdeCollider,
+    pub(crate) transform: SerdeTransform,
+}
+impl Prefab for TriggerZoneData {
+    type Query = SensorQuery<TriggerZone>;
+
+    fn from_query((zone, collider, transform, name): QueryItem<Self::Query>) -> Self {
+        TriggerZoneData {
+            name: name.to_string(),
+            when: zone.when,
+            actions: zone.actions.clone(),
+            collider: saved_collider(collider),
+            transform: (*transform).into(),
+        }
+    }
+    fn spawn(self, cmds: &mut EntityCommands) {
+        let Self { name, when, actions, collider, transform } = self;
+        let zone = TriggerZone::new(when, actions);
+        spawn_sensor(cmds, zone, groups::MUSIC, name, collider, transform);
+    }
+}
+
+/// A [`ForceVolume`], pushing the klod and agglomerables inside it.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Debug, Deserialize, Clone)]
+pub(crate) struct ForceVolumeData {
+    pub(crate) name: String,
+    pub(crate) force: ForceKind,
+    pub(crate) collider: SerdeCollider,
+    pub(crate) transform: SerdeTransform,
+}
+impl Prefab for ForceVolumeData {
+    type Query = SensorQuery<ForceVolume>;
+
+    fn from_query((volume, collider, transform, name): QueryItem<Self::Query>) -> Self {
+        ForceVolumeData {
+            name: name.to_string(),
+            force: volume.0,
+            collider: saved_collider(collider),
+            transform: (*transform).into(),
+        }
+    }
+    fn spawn(self, cmds: &mut EntityCommands) {
+        let Self { name, force, collider, transform } = self;
+        spawn_sensor(
+            cmds,
+            ForceVolume(force),
+            groups::FORCE,
+            name,
+            collider,
+            transform,
+        );
     }
 }
@@ -0,0 +1,130 @@
+//! Meshes of the exact shape of [`Collider`]s, for the editor's pick boxes.
+//!
+//! Convex shapes without flat faces only have a support function, their
+//! surface is sampled in a fixed number of directions.
+use bevy::{prelude::*, render::mesh::PrimitiveTopology};
+use bevy_rapier3d::{
+    prelude::Collider,
+    rapier::parry::{
+        math::{Isometry, Point, Real, Vector},
+        shape::{ConvexPolyhedron, Shape},
+    },
+};
+
+/// How many points of the surface of round shapes are part of their mesh.
+const MESH_SAMPLES: usize = 400;
+/// How many points of the surface of round shapes are in [`hull_points`].
+const HULL_SAMPLES: usize = 100;
+
+/// `samples` directions evenly spread on the unit sphere, following a
+/// fibonacci spiral.
+fn sphere_directions(samples: usize) -> impl Iterator<Item = Vector<Real>> {
+    let golden_angle = std::f32::consts::PI * (3.0 - 5.0_f32.sqrt());
+    (0..samples).map(move |i| {
+        let y = 1.0 - 2.0 * (i as f32 + 0.5) / samples as f32;
+        let radius = (1.0 - y * y).sqrt();
+        let theta = golden_angle * i as f32;
+        Vector::new(radius * theta.cos(), y, radius * theta.sin())
+    })
+}
+
+/// Add the triangles of `shape` moved by `isometry` to `triangles`.
+fn shape_triangles(
+    shape: &dyn Shape,
+    isometry: &Isometry<Real>,
+    samples: usize,
+    triangles: &mut Vec<[Vec3; 3]>,
+) {
+    if let Some(compound) = shape.as_compound() {
+        for (part_isometry, part) in compound.shapes() {
+            shape_triangles(&**part, &(isometry * part_isometry), samples, triangles);
+        }
+        return;
+    }
+    let mut extend = |vertices: &[Point<Real>], indices: &[[u32; 3]]| {
+        let vertex = |i: u32| {
+            let p = isometry * vertices[i as usize];
+            Vec3::new(p.x, p.y, p.z)
+        };
+        triangles.extend(indices.iter().map(|&t| t.map(vertex)));
+    };
+    if let Some(trimesh) = shape.as_trimesh() {
+        extend(trimesh.vertices(), trimesh.indices());
+    } else if let Some(polyhedron) = shape.as_convex_polyhedron() {
+        let (vertices, indices) = polyhedron.to_trimesh();
+        extend(&vertices, &indices);
+    } else if let Some(support_map) = shape.as_support_map() {
+        let surface: Vec<_> = sphere_directions(samples)
+            .map(|direction| support_map.local_support_point(&direction))
+            .collect();
+        // Shapes with a zero size have no surface.
+        if let Some(hull) = ConvexPolyhedron::from_convex_hull(&surface) {
+            let (vertices, indices) = hull.to_trimesh();
+            extend(&vertices, &indices);
+        }
+    } else {
+        let (vertices, indices) = shape.compute_local_aabb().to_trimesh();
+        extend(&vertices, &indices);
+    }
+}
+
+/// A flat shaded mesh of `triangles`.
+pub(super) fn flat_mesh(triangles: impl IntoIterator<Item = [Vec3; 3]>) -> Mesh {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    for [a, b, c] in triangles {
+        let normal = (b - a).cross(c - a).normalize_or_zero();
+        positions.extend([a, b, c].map(Vec3::to_array));
+        normals.extend([normal.to_array(); 3]);
+    }
+    let uvs = vec![[0.0, 0.0]; positions.len()];
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
+}
+
+/// A mesh of the shape of `collider`, not scaled by the entity's transform.
+pub(crate) fn collider_mesh(collider: &Collider) -> Mesh {
+    // `raw` is already scaled by the transform, which also applies to the mesh.
+    baked_collider_mesh(collider, collider.scale())
+}
+
+/// A mesh of the shape of `collider`, which has the `scale` of its entity's
+/// transform baked in, see `ColliderScale::Absolute`.
+pub(crate) fn baked_collider_mesh(collider: &Collider, scale: Vec3) -> Mesh {
+    let mut triangles = Vec::new();
+    shape_triangles(
+        &*collider.raw,
+        &Isometry::identity(),
+        MESH_SAMPLES,
+        &mut triangles,
+    );
+    flat_mesh(triangles.into_iter().map(|t| t.map(|v| v / scale)))
+}
+
+/// Vertices of the convex hull of `collider`, an approximation for round shapes.
+pub(crate) fn hull_points(collider: &Collider) -> Vec<Vec3> {
+    let mut triangles = Vec::new();
+    shape_triangles(
+        &*collider.raw,
+        &Isometry::identity(),
+        HULL_SAMPLES,
+        &mut triangles,
+    );
+    let points: Vec<_> = triangles
+        .iter()
+        .flatten()
+        .map(|v| Point::new(v.x, v.y, v.z))
+        .collect();
+    // Flat shapes, such as ones scaled to zero, have no hull: keep all their points.
+    match ConvexPolyhedron::from_convex_hull(&points) {
+        Some(hull) => hull
+            .points()
+            .iter()
+            .map(|p| Vec3::new(p.x, p.y, p.z))
+            .collect(),
+        None => triangles.into_iter().flatten().collect(),
+    }
+}
@@ -0,0 +1,76 @@
+//! Level rules, consequences of gameplay events declared in the level file.
+//!
+//! A rule such as "when the obstacle `SpecialDoor.002` is destroyed, spawn
+//! these objects and switch music" is a [`LevelRule`], stored in the
+//! `rules` field of the level.
+use bevy::prelude::{Plugin as BevyPlugin, *};
+use bevy_debug_text_overlay::screen_print;
+use serde::Deserialize;
+
+use crate::{
+    scene::PhysicsObject,
+    state::GameState,
+    system_helper::EasySystemSetCtor,
+    trigger::{RunAction, TriggerAction},
+};
+
+/// A gameplay event rules can react to, objects are identified by their name.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+pub(crate) enum LevelEvent {
+    /// An elemental obstacle was destroyed.
+    ObstacleDestroyed(String),
+    /// An agglomerable was added to the klod.
+    Agglomerated(String),
+    /// A trigger zone ran its actions.
+    ZoneTriggered(String),
+}
+
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct LevelRule {
+    pub(crate) when: LevelEvent,
+    /// Same actions as trigger zones.
+    #[serde(default)]
+    pub(crate) actions: Vec<TriggerAction>,
+    /// Objects added to the level.
+    #[serde(default)]
+    pub(crate) spawn: Vec<PhysicsObject>,
+}
+
+/// An object spawned by a [`LevelRule`], not saved with the level.
+#[derive(Component)]
+pub(crate) struct RuleSpawned;
+
+/// The rules of the current level, including the rules of its rooms.
+#[derive(Default)]
+pub(crate) struct LevelRules(pub(crate) Vec<LevelRule>);
+
+fn run_rules(
+    mut cmds: Commands,
+    mut events: EventReader<LevelEvent>,
+    mut actions: EventWriter<RunAction>,
+    rules: Res<LevelRules>,
+    assets: Res<AssetServer>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    for event in events.iter() {
+        for rule in rules.0.iter().filter(|rule| rule.when == *event) {
+            screen_print!(sec: 3.0, col: Color::LIME_GREEN, "level rule: {event:?}");
+            actions.send_batch(rule.actions.iter().cloned().map(RunAction));
+            for object in &rule.spawn {
+                let spawned = object.clone().spawn(&mut cmds, &assets, &mut meshes, false);
+                cmds.entity(spawned).insert(RuleSpawned);
+            }
+        }
+    }
+}
+
+pub(crate) struct Plugin;
+impl BevyPlugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelRules>()
+            .add_event::<LevelEvent>()
+            .add_system_set(GameState::Playing.on_update(run_rules));
+    }
+}
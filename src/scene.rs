@@ -1,24 +1,34 @@
-#[cfg(feature = "editor")]
+mod binary;
+mod light;
+pub(crate) mod lint;
+mod metadata;
 mod migration;
+mod room;
+mod streaming;
+mod template;
 
+#[cfg(feature = "editor")]
+use std::error::Error;
 use std::{
-    error::Error,
+    collections::BTreeMap,
+    mem::{discriminant, Discriminant},
     path::{Path, PathBuf},
 };
 
 #[cfg(feature = "editor")]
 use bevy::ui::FocusPolicy;
 use bevy::{
-    asset::AssetPath,
+    asset::{AssetLoader, AssetPath, HandleId, LoadContext, LoadState, LoadedAsset},
     ecs::{
         query::{QueryItem, ReadOnlyWorldQuery, WorldQuery, WorldQueryGats},
-        system::{EntityCommands, SystemParam, SystemState},
+        system::{Command, EntityCommands, SystemParam, SystemState},
     },
     math::Vec3A,
     prelude::{Plugin as BevyPlugin, *},
+    reflect::TypeUuid,
     render::primitives::{Aabb, Sphere},
     scene::{InstanceId, SceneInstance},
-    utils::HashMap,
+    utils::{BoxedFuture, FloatOrd, HashMap, HashSet},
 };
 #[cfg(feature = "editor")]
 use bevy_editor_pls_default_windows::hierarchy::picking::IgnoreEditorRayCast;
@@ -31,20 +41,65 @@ use serde::Deserialize;
 
 use crate::{
     audio::ImpactSound,
-    ball::{Agglomerable, Klod, KlodSpawnTransform},
+    ball::{
+        slurp::{LevelSlurpRule, SlurpRules},
+        tuning::{KlodTuning, LevelTuning},
+        Agglomerable, Klod, KlodSpawnTransform,
+    },
+    campaign::PlayingCampaign,
+    checkpoint::{Checkpoint, KillZone, RespawnRules},
     collision_groups as groups,
+    force::ForceVolume,
     game_audio::{MusicTrigger, NoiseOnHit},
+    kinematic::Kinematic,
+    pickup::TimePickup,
     powers::{ElementalObstacle, Power},
-    prefabs::{AggloData, MusicTriggerData, Prefab, Scenery, SerdeCollider, SerdeTransform},
+    prefabs::{
+        baked_collider_mesh, collider_mesh, insert_collider, is_uniform, saved_collider, AggloData,
+        CheckpointData, ForceVolumeData, KillZoneData, KinematicData, LevelCollider,
+        MusicTriggerData, Prefab, SavedColliderQuery, Scenery, SerdeCollider, SerdeTransform,
+        TimePickupData, TriggerZoneData,
+    },
+    rules::{LevelRule, LevelRules, RuleSpawned},
     score::{FinishLine, GameData},
+    trigger::TriggerZone,
     LightSwitch,
 };
+pub(crate) use light::AnyLight;
+use light::{LevelLighting, LightKind, LightQuery, SerdeLight};
+use metadata::LevelMetadata;
+pub(crate) use metadata::{level_file, list_levels, LevelEntry};
+use room::{InRoom, Include, Room};
+use streaming::{LevelStreaming, Streamed};
+use template::ObjectTemplate;
 
 pub(crate) struct CurrentScene(pub(crate) KlodScene);
 
+/// The level file currently played, the level is respawned when it is (re)loaded.
+struct LevelHandle(Handle<KlodScene>);
+
+/// Level files written by [`KlodScene::save`], the next time they are modified
+/// on disk is our own save, which shouldn't respawn the level.
+#[derive(Default)]
+struct SavedFiles(HashSet<HandleId>);
+
+/// Identifies a [`PhysicsObject`] across loads and saves of a level.
+#[derive(Component, Clone, Copy)]
+pub(crate) struct ObjectId(u32);
+
+/// The template a [`PhysicsObject`] was loaded with, it is saved with it again.
+#[derive(Component, Clone)]
+pub(crate) struct Templated(String);
+
 #[cfg_attr(feature = "editor", derive(serde::Serialize))]
 #[derive(Deserialize, Debug, Clone)]
 pub(crate) struct PhysicsObject {
+    /// `None` for objects not saved yet.
+    #[serde(default)]
+    id: Option<u32>,
+    /// Not written in binary levels, which have their templates applied.
+    #[serde(skip)]
+    template: Option<String>,
     name: String,
     asset_path: Option<AssetPath<'static>>,
     transform: SerdeTransform,
@@ -61,13 +116,17 @@ where
     for<'w> QueryItem<'w, Q>: Into<ObjectType>,
     for<'w> <Q as WorldQueryGats<'w>>::Fetch: Clone,
 {
+    id: Option<&'static ObjectId>,
+    template: Option<&'static Templated>,
     name: Option<&'static Name>,
     sounds: &'static NoiseOnHit,
-    scene: Option<&'static Handle<Scene>>,
+    scene: Option<&'static Streamed>,
     transform: &'static Transform,
     friction: &'static Friction,
     restitution: &'static Restitution,
-    collider: &'static Collider,
+    collider: SavedColliderQuery,
+    kinematic: Option<&'static Kinematic>,
+    room: Option<&'static InRoom>,
     object: Q,
 }
 impl<'w, Q> ObjectQueryItem<'w, Q>
@@ -78,18 +137,25 @@ where
 {
     fn data(self, assets: &AssetServer) -> PhysicsObject {
         PhysicsObject {
+            id: self.id.map(|id| id.0),
+            template: self.template.map(|Templated(name)| name.clone()),
             sounds: self.sounds.noises.to_vec(),
             asset_path: self
                 .scene
-                .and_then(|h| assets.get_handle_path(h))
+                .and_then(|Streamed(scene)| assets.get_handle_path(scene))
                 .map(|t| t.to_owned()),
-            transform: (*self.transform).into(),
+            // Kinematic objects move away from their position in the level.
+            transform: self
+                .kinematic
+                .and_then(|k| k.origin)
+                .unwrap_or(*self.transform)
+                .into(),
             object: self.object.into(),
             name: self
                 .name
                 .and_then(|name| (name.as_str() != "").then(|| name.to_string()))
                 .unwrap_or_else(|| "Unamed Physics Object".to_owned()),
-            collider: self.collider.into(),
+            collider: saved_collider(self.collider),
             friction: self.friction.coefficient,
             restitution: self.restitution.coefficient,
         }
@@ -108,6 +174,8 @@ impl PhysicsObject {
         object: ObjectType,
     ) -> Self {
         Self {
+            id: None,
+            template: None,
             name,
             sounds,
             asset_path: asset_path.map(|p| AssetPath::from(&p).to_owned()),
@@ -124,7 +192,6 @@ impl PhysicsObject {
             .insert_bundle(SpatialBundle::from_transform(self.transform.into()))
             .insert_bundle((
                 NoiseOnHit { noises: self.sounds.iter().cloned().collect() },
-                Collider::from(self.collider.clone()),
                 Friction {
                     coefficient: self.friction,
                     combine_rule: CoefficientCombineRule::Max,
@@ -134,6 +201,7 @@ impl PhysicsObject {
                     combine_rule: CoefficientCombineRule::Max,
                 },
             ));
+        insert_collider(object, self.collider.clone());
         #[cfg(feature = "editor")]
         object.insert_bundle((
             PickableMesh::default(),
@@ -145,6 +213,8 @@ impl PhysicsObject {
         match self.object {
             ObjectType::Scenery(scenery_data) => scenery_data.spawn(object),
             ObjectType::Agglomerable(agglo_data) => agglo_data.spawn(object),
+            ObjectType::Kinematic(kinematic_data) => kinematic_data.spawn(object),
+            ObjectType::TimePickup(pickup_data) => pickup_data.spawn(object),
         };
     }
     pub(crate) fn spawn(
@@ -153,19 +223,38 @@ impl PhysicsObject {
         assets: &AssetServer,
         meshes: &mut Assets<Mesh>,
         compute_aabb: bool,
-    ) {
-        let mut object = match self.asset_path {
-            Some(path) => cmds.spawn_bundle(SceneBundle {
-                scene: assets.load(path),
-                transform: self.transform.into(),
-                ..default()
-            }),
-            None => cmds.spawn_bundle(SpatialBundle::from_transform(self.transform.into())),
-        };
+    ) -> Entity {
+        let mut object = cmds.spawn_bundle(SpatialBundle::from_transform(self.transform.into()));
+        if let Some(path) = self.asset_path {
+            object.insert(Streamed(assets.load(path)));
+        }
+        // Bake non-uniform scales into the collider to keep its proportions,
+        // rapier already scales triangle meshes exactly.
+        let scale = self.transform.scale;
+        let bake_scale =
+            !is_uniform(scale) && !matches!(self.collider, SerdeCollider::TriMesh { .. });
+        if bake_scale {
+            let level_collider = LevelCollider(self.collider.clone());
+            match (self.collider.clone() * scale).to_collider() {
+                Some(collider) => {
+                    object.insert_bundle((
+                        collider,
+                        ColliderScale::Absolute(Vec3::ONE),
+                        level_collider,
+                    ));
+                    #[cfg(feature = "editor")]
+                    object.insert(BakedScale(scale));
+                }
+                None => {
+                    object.insert(level_collider);
+                }
+            }
+        } else {
+            insert_collider(&mut object, self.collider.clone());
+        }
         object.insert_bundle((
             Name::new(self.name),
             NoiseOnHit { noises: self.sounds.iter().cloned().collect() },
-            Collider::from(self.collider.clone()),
             Friction {
                 coefficient: self.friction,
                 combine_rule: CoefficientCombineRule::Max,
@@ -178,6 +267,12 @@ impl PhysicsObject {
         if compute_aabb {
             object.insert(ComputeDefaultAabb);
         }
+        if let Some(id) = self.id {
+            object.insert(ObjectId(id));
+        }
+        if let Some(template) = self.template {
+            object.insert(Templated(template));
+        }
         #[cfg(feature = "editor")]
         object.insert_bundle((
             meshes.add(self.collider.into()),
@@ -193,15 +288,20 @@ impl PhysicsObject {
         match self.object {
             ObjectType::Scenery(scenery_data) => scenery_data.spawn(&mut object),
             ObjectType::Agglomerable(agglo_data) => agglo_data.spawn(&mut object),
+            ObjectType::Kinematic(kinematic_data) => kinematic_data.spawn(&mut object),
+            ObjectType::TimePickup(pickup_data) => pickup_data.spawn(&mut object),
         };
+        object.id()
     }
 }
 
 #[cfg_attr(feature = "editor", derive(serde::Serialize))]
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub(crate) enum ObjectType {
     Scenery(Scenery),
     Agglomerable(AggloData),
+    Kinematic(KinematicData),
+    TimePickup(TimePickupData),
 }
 impl<'w> From<(&'w Scenery, Option<&'w ElementalObstacle>)> for ObjectType {
     fn from(item: QueryItem<'w, <Scenery as Prefab>::Query>) -> Self {
@@ -215,16 +315,37 @@ impl<'w> From<(&'w Agglomerable, &'w Power)> for ObjectType {
     }
 }
 
+impl<'w> From<&'w Kinematic> for ObjectType {
+    fn from(item: QueryItem<'w, <KinematicData as Prefab>::Query>) -> Self {
+        ObjectType::Kinematic(Prefab::from_query(item))
+    }
+}
+
+impl<'w> From<&'w TimePickup> for ObjectType {
+    fn from(item: QueryItem<'w, <TimePickupData as Prefab>::Query>) -> Self {
+        ObjectType::TimePickup(Prefab::from_query(item))
+    }
+}
+
 #[derive(SystemParam)]
 struct KlodSceneQuery<'w, 's> {
     assets: Res<'w, AssetServer>,
     timer: Res<'w, GameData>,
-    agglomerables: Query<'w, 's, ObjectQuery<<AggloData as Prefab>::Query>>,
-    scenery: Query<'w, 's, ObjectQuery<<Scenery as Prefab>::Query>>,
-    music: Query<'w, 's, <MusicTriggerData as Prefab>::Query>,
+    // Objects spawned by level rules are not part of the level.
+    agglomerables: Query<'w, 's, ObjectQuery<<AggloData as Prefab>::Query>, Without<RuleSpawned>>,
+    scenery: Query<'w, 's, ObjectQuery<<Scenery as Prefab>::Query>, Without<RuleSpawned>>,
+    kinematics: Query<'w, 's, ObjectQuery<<KinematicData as Prefab>::Query>, Without<RuleSpawned>>,
+    time_pickups:
+        Query<'w, 's, ObjectQuery<<TimePickupData as Prefab>::Query>, Without<RuleSpawned>>,
+    music: Query<'w, 's, (Option<&'static InRoom>, <MusicTriggerData as Prefab>::Query)>,
+    checkpoints: Query<'w, 's, (Option<&'static InRoom>, <CheckpointData as Prefab>::Query)>,
+    kill_zones: Query<'w, 's, (Option<&'static InRoom>, <KillZoneData as Prefab>::Query)>,
+    trigger_zones: Query<'w, 's, (Option<&'static InRoom>, <TriggerZoneData as Prefab>::Query)>,
+    force_volumes: Query<'w, 's, (Option<&'static InRoom>, <ForceVolumeData as Prefab>::Query)>,
     klod_spawn: Res<'w, KlodSpawnTransform>,
-    finish_zone: Query<'w, 's, (&'static Collider, &'static Transform), With<FinishLine>>,
-    lights: Query<'w, 's, (&'static PointLight, &'static Transform)>,
+    finish_zone: Query<'w, 's, (SavedColliderQuery, &'static Transform), With<FinishLine>>,
+    lights: Query<'w, 's, LightQuery, AnyLight>,
+    current: Option<Res<'w, CurrentScene>>,
 }
 #[derive(SystemParam)]
 struct KlodSweepQuery<'w, 's> {
@@ -233,11 +354,17 @@ struct KlodSweepQuery<'w, 's> {
         's,
         Entity,
         Or<(
-            With<PointLight>,
+            AnyLight,
             With<Scenery>,
             With<Agglomerable>,
+            With<Kinematic>,
+            With<TimePickup>,
             With<MusicTrigger>,
             With<FinishLine>,
+            With<Checkpoint>,
+            With<KillZone>,
+            With<TriggerZone>,
+            With<ForceVolume>,
         )>,
     >,
 }
@@ -252,52 +379,15 @@ struct FinishZone {
     collider: SerdeCollider,
     transform: SerdeTransform,
 }
-impl<'a> From<(&'a Collider, &'a Transform)> for FinishZone {
-    fn from((collider, transform): (&'a Collider, &'a Transform)) -> Self {
+impl<'a> From<(QueryItem<'a, SavedColliderQuery>, &'a Transform)> for FinishZone {
+    fn from((collider, transform): (QueryItem<'a, SavedColliderQuery>, &'a Transform)) -> Self {
         FinishZone {
-            collider: collider.into(),
+            collider: saved_collider(collider),
             transform: (*transform).into(),
         }
     }
 }
 
-#[cfg_attr(feature = "editor", derive(serde::Serialize))]
-#[derive(Deserialize, Debug, Clone)]
-struct SerdeLight {
-    intensity: f32,
-    color: Color,
-    position: SerdeTransform,
-    radius: f32,
-}
-impl<'a> From<(&'a PointLight, &'a Transform)> for SerdeLight {
-    fn from((light, transform): (&'a PointLight, &'a Transform)) -> Self {
-        SerdeLight {
-            intensity: light.intensity,
-            color: light.color,
-            position: (*transform).into(),
-            radius: light.radius,
-        }
-    }
-}
-impl SerdeLight {
-    fn spawn(&self, cmds: &mut Commands, is_visible: bool) {
-        let range = if self.intensity == 55555.0 { 30.0 } else { 20.0 };
-        cmds.spawn_bundle(PointLightBundle {
-            transform: self.position.into(),
-            point_light: PointLight {
-                color: self.color,
-                intensity: self.intensity,
-                radius: self.radius,
-                shadows_enabled: false,
-                range,
-                ..default()
-            },
-            visibility: Visibility { is_visible },
-            ..default()
-        });
-    }
-}
-
 #[derive(SystemParam)]
 struct KlodSpawnQuery<'w, 's> {
     cmds: Commands<'w, 's>,
@@ -305,17 +395,47 @@ struct KlodSpawnQuery<'w, 's> {
     meshes: ResMut<'w, Assets<Mesh>>,
     klod: Query<'w, 's, Entity, With<Klod>>,
     light_switch: Res<'w, LightSwitch>,
+    campaign: Option<Res<'w, PlayingCampaign>>,
 }
-#[cfg_attr(feature = "editor", derive(serde::Serialize))]
-#[derive(Deserialize, Debug, Clone)]
+/// Version of the [`KlodScene`] format, saved as the `version` field of `.klodlvl` files.
+///
+/// Bump this and add an upgrade step in [`migration`] when changing [`KlodScene`].
+const SCENE_VERSION: u32 = 17;
+
+/// A level, with all [`template`]s already applied to its objects.
+#[cfg_attr(
+    feature = "editor",
+    derive(serde::Serialize),
+    serde(into = "template::KlodSceneFile")
+)]
+#[derive(Deserialize, Debug, Clone, TypeUuid)]
+#[serde(try_from = "template::KlodSceneFile")]
+#[uuid = "4c6a3b0e-5f7d-4a8e-9b21-d3e0f6a1c872"]
 pub(crate) struct KlodScene {
+    version: u32,
+    metadata: LevelMetadata,
     klod_spawn_transform: SerdeTransform,
     finish_zone: FinishZone,
     game_timer_seconds: f32,
+    /// Templates of the level file, objects loaded with a template are
+    /// saved with the same template.
+    templates: BTreeMap<String, ObjectTemplate>,
     objects: Vec<PhysicsObject>,
     music_triggers: Vec<MusicTriggerData>,
     required_score: f32,
     lights: Vec<SerdeLight>,
+    lighting: LevelLighting,
+    checkpoints: Vec<CheckpointData>,
+    kill_zones: Vec<KillZoneData>,
+    trigger_zones: Vec<TriggerZoneData>,
+    force_volumes: Vec<ForceVolumeData>,
+    respawn: RespawnRules,
+    rules: Vec<LevelRule>,
+    slurp_rules: Vec<LevelSlurpRule>,
+    tuning: Option<KlodTuning>,
+    includes: Vec<Include>,
+    /// All levels included by `includes`, see [`room`].
+    rooms: Vec<Room>,
 }
 #[derive(SystemParam)]
 struct KlodCopyQuery<'w, 's> {
@@ -324,6 +444,8 @@ struct KlodCopyQuery<'w, 's> {
     meshes: ResMut<'w, Assets<Mesh>>,
     agglomerables: Query<'w, 's, ObjectQuery<<AggloData as Prefab>::Query>>,
     scenery: Query<'w, 's, ObjectQuery<<Scenery as Prefab>::Query>>,
+    kinematics: Query<'w, 's, ObjectQuery<<KinematicData as Prefab>::Query>>,
+    time_pickups: Query<'w, 's, ObjectQuery<<TimePickupData as Prefab>::Query>>,
 }
 impl KlodScene {
     #[cfg(feature = "editor")]
@@ -332,6 +454,8 @@ impl KlodScene {
         let KlodCopyQuery {
             agglomerables,
             scenery,
+            kinematics,
+            time_pickups,
             assets,
             mut cmds,
             mut meshes,
@@ -340,6 +464,8 @@ impl KlodScene {
         let mut to_copy = Vec::new();
         to_copy.extend(agglomerables.iter_many(o).map(|item| item.data(&assets)));
         to_copy.extend(scenery.iter_many(o).map(|item| item.data(&assets)));
+        to_copy.extend(kinematics.iter_many(o).map(|item| item.data(&assets)));
+        to_copy.extend(time_pickups.iter_many(o).map(|item| item.data(&assets)));
 
         for mut object in to_copy.into_iter() {
             let prefix = object.name.trim_end_matches(char::is_numeric);
@@ -351,41 +477,110 @@ impl KlodScene {
             } else {
                 format!("Copy of {}", object.name)
             };
+            object.id = None;
             object.spawn(&mut cmds, &assets, &mut meshes, false);
         }
         query.apply(world);
     }
     fn spawn(
         self,
-        KlodSpawnQuery { cmds, assets, meshes, klod, light_switch }: &mut KlodSpawnQuery,
+        KlodSpawnQuery { cmds, assets, meshes, klod, light_switch, campaign }: &mut KlodSpawnQuery,
     ) {
         let klod_spawn = self.klod_spawn_transform.into();
+        let required_score = campaign
+            .as_ref()
+            .map_or(self.required_score, |c| c.required_score);
 
         cmds.insert_resource(CurrentScene(self.clone()));
-        cmds.insert_resource(GameData::new(self.game_timer_seconds, self.required_score));
+        cmds.insert_resource(GameData::new(self.game_timer_seconds, required_score));
         cmds.insert_resource(KlodSpawnTransform(klod_spawn));
+        cmds.insert_resource(self.lighting.ambient(light_switch));
+        cmds.insert_resource(self.lighting.clear_color());
+        cmds.insert_resource(self.respawn.clone());
+        cmds.insert_resource(LevelTuning(self.tuning.clone()));
+        let room_rules = self.rooms.iter().flat_map(|room| {
+            room.scene.rules.iter().cloned().map(|mut rule| {
+                for object in &mut rule.spawn {
+                    object.transform = room.to_world(object.transform);
+                }
+                rule
+            })
+        });
+        let rules = self.rules.iter().cloned().chain(room_rules).collect();
+        cmds.insert_resource(LevelRules(rules));
+        let room_slurp_rules = self.rooms.iter().flat_map(|room| &room.scene.slurp_rules);
+        let slurp_rules = self.slurp_rules.iter().chain(room_slurp_rules).cloned();
+        cmds.insert_resource(SlurpRules::new(slurp_rules));
 
-        cmds.spawn_bundle((
+        let mut finish_zone = cmds.spawn_bundle((
             Name::new("Finish Zone"),
             FinishLine,
             Sensor,
             groups::MUSIC,
             Transform::from(self.finish_zone.transform),
             GlobalTransform::default(),
-            Collider::from(self.finish_zone.collider),
         ));
+        insert_collider(&mut finish_zone, self.finish_zone.collider);
 
-        for object in self.objects.into_iter() {
-            object.spawn(cmds, assets, meshes, false);
+        let rooms = &self.rooms;
+        for (room, object) in room::with_rooms(self.objects, rooms, |s| &s.objects) {
+            let entity = object.spawn(cmds, assets, meshes, false);
+            if let Some(room) = room {
+                cmds.entity(entity).insert(room);
+            }
         }
-        for music in self.music_triggers.into_iter() {
+        for (room, music) in room::with_rooms(self.music_triggers, rooms, |s| &s.music_triggers) {
             let mut cmds = cmds.spawn();
             #[cfg(feature = "editor")]
             cmds.insert(meshes.add(music.collider.clone().into()));
             music.spawn(&mut cmds);
+            if let Some(room) = room {
+                cmds.insert(room);
+            }
+        }
+        for (room, light) in room::with_rooms(self.lights, rooms, |s| &s.lights) {
+            let entity = light.spawn(cmds, light_switch.on);
+            if let Some(room) = room {
+                cmds.entity(entity).insert(room);
+            }
+        }
+        for (room, checkpoint) in room::with_rooms(self.checkpoints, rooms, |s| &s.checkpoints) {
+            let mut cmds = cmds.spawn();
+            #[cfg(feature = "editor")]
+            cmds.insert(meshes.add(checkpoint.collider.clone().into()));
+            checkpoint.spawn(&mut cmds);
+            if let Some(room) = room {
+                cmds.insert(room);
+            }
+        }
+        for (room, kill_zone) in room::with_rooms(self.kill_zones, rooms, |s| &s.kill_zones) {
+            let mut cmds = cmds.spawn();
+            #[cfg(feature = "editor")]
+            cmds.insert(meshes.add(kill_zone.collider.clone().into()));
+            kill_zone.spawn(&mut cmds);
+            if let Some(room) = room {
+                cmds.insert(room);
+            }
+        }
+        let trigger_zones = room::with_rooms(self.trigger_zones, rooms, |s| &s.trigger_zones);
+        for (room, trigger_zone) in trigger_zones {
+            let mut cmds = cmds.spawn();
+            #[cfg(feature = "editor")]
+            cmds.insert(meshes.add(trigger_zone.collider.clone().into()));
+            trigger_zone.spawn(&mut cmds);
+            if let Some(room) = room {
+                cmds.insert(room);
+            }
         }
-        for light in self.lights.into_iter() {
-            light.spawn(cmds, light_switch.on);
+        let force_volumes = room::with_rooms(self.force_volumes, rooms, |s| &s.force_volumes);
+        for (room, force_volume) in force_volumes {
+            let mut cmds = cmds.spawn();
+            #[cfg(feature = "editor")]
+            cmds.insert(meshes.add(force_volume.collider.clone().into()));
+            force_volume.spawn(&mut cmds);
+            if let Some(room) = room {
+                cmds.insert(room);
+            }
         }
 
         let klod = match klod.get_single() {
@@ -403,52 +598,206 @@ impl KlodScene {
             assets,
             agglomerables,
             scenery,
+            kinematics,
+            time_pickups,
             klod_spawn,
             music,
+            checkpoints: checkpoint_query,
+            kill_zones: kill_zone_query,
+            trigger_zones: trigger_zone_query,
+            force_volumes: force_volume_query,
             timer,
             finish_zone,
             lights,
+            current,
         }: &KlodSceneQuery,
     ) -> Self {
-        let mut objects = Vec::with_capacity(agglomerables.iter().len() + scenery.iter().len());
-        objects.extend(agglomerables.iter().map(|item| item.data(assets)));
-        objects.extend(scenery.iter().map(|item| item.data(assets)));
-        let music_triggers = music.iter().map(|t| Prefab::from_query(t)).collect();
-        let mut lights: Vec<SerdeLight> = lights.iter().map(Into::into).collect();
-        let mut positions: Vec<Vec3> = Vec::new();
-        lights.retain(|light| {
-            let current_pos = light.position.translation;
-            let new = !positions.iter().any(|p| p.abs_diff_eq(current_pos, 0.01));
-            if !new {
-                positions.push(current_pos);
+        let mut rooms: Vec<Room> = current
+            .iter()
+            .flat_map(|current| current.0.rooms.iter().map(Room::emptied))
+            .collect();
+        let includes = current
+            .as_ref()
+            .map_or(Vec::new(), |c| c.0.includes.clone());
+        // Not read from the `AmbientLight` resource, which depends on the `LightSwitch`.
+        let lighting = current
+            .as_ref()
+            .map_or_else(LevelLighting::default, |c| c.0.lighting.clone());
+        let metadata = current
+            .as_ref()
+            .map_or_else(LevelMetadata::default, |c| c.0.metadata.clone());
+        let respawn = current
+            .as_ref()
+            .map_or_else(RespawnRules::default, |c| c.0.respawn.clone());
+        let rules = current.as_ref().map_or(Vec::new(), |c| c.0.rules.clone());
+        let templates = current
+            .as_ref()
+            .map_or_else(BTreeMap::new, |c| c.0.templates.clone());
+        let slurp_rules = current
+            .as_ref()
+            .map_or(Vec::new(), |c| c.0.slurp_rules.clone());
+        let tuning = current.as_ref().and_then(|c| c.0.tuning.clone());
+        // `KlodSpawnTransform` is moved to the last checkpoint the klod reached.
+        let klod_spawn_transform = current
+            .as_ref()
+            .map_or(klod_spawn.0.into(), |c| c.0.klod_spawn_transform);
+
+        let all_objects = agglomerables
+            .iter()
+            .map(|item| (item.room.copied(), item.data(assets)))
+            .chain(
+                scenery
+                    .iter()
+                    .map(|item| (item.room.copied(), item.data(assets))),
+            )
+            .chain(
+                kinematics
+                    .iter()
+                    .map(|item| (item.room.copied(), item.data(assets))),
+            )
+            .chain(
+                time_pickups
+                    .iter()
+                    .map(|item| (item.room.copied(), item.data(assets))),
+            );
+        let objects = room::split_by_room(all_objects, &mut rooms, |s| &mut s.objects);
+        let music = music
+            .iter()
+            .map(|(room, item)| (room.copied(), MusicTriggerData::from_query(item)));
+        let music_triggers = room::split_by_room(music, &mut rooms, |s| &mut s.music_triggers);
+        let checkpoints = checkpoint_query
+            .iter()
+            .map(|(room, item)| (room.copied(), CheckpointData::from_query(item)));
+        let checkpoints = room::split_by_room(checkpoints, &mut rooms, |s| &mut s.checkpoints);
+        let kill_zones = kill_zone_query
+            .iter()
+            .map(|(room, item)| (room.copied(), KillZoneData::from_query(item)));
+        let kill_zones = room::split_by_room(kill_zones, &mut rooms, |s| &mut s.kill_zones);
+        let trigger_zones = trigger_zone_query
+            .iter()
+            .map(|(room, item)| (room.copied(), TriggerZoneData::from_query(item)));
+        let trigger_zones =
+            room::split_by_room(trigger_zones, &mut rooms, |s| &mut s.trigger_zones);
+        let force_volumes = force_volume_query
+            .iter()
+            .map(|(room, item)| (room.copied(), ForceVolumeData::from_query(item)));
+        let force_volumes =
+            room::split_by_room(force_volumes, &mut rooms, |s| &mut s.force_volumes);
+        let mut all_lights: Vec<_> = lights
+            .iter()
+            .filter_map(|item| Some((item.room.copied(), item.data()?)))
+            .collect();
+        // Keep one light of each kind per room and place, the rotation is
+        // compared too since directional lights ignore their translation.
+        let mut kept: Vec<(Option<usize>, Discriminant<LightKind>, SerdeTransform)> = Vec::new();
+        all_lights.retain(|(room, light)| {
+            let room = room.map(|InRoom(i)| i);
+            let kind = discriminant(&light.kind);
+            let at = light.position;
+            let same_place = |p: &SerdeTransform| {
+                p.translation.abs_diff_eq(at.translation, 0.01)
+                    && p.rotation.abs_diff_eq(at.rotation, 0.01)
+            };
+            let new = !kept
+                .iter()
+                .any(|(r, k, p)| *r == room && *k == kind && same_place(p));
+            if new {
+                kept.push((room, kind, at));
             }
             new
         });
-        KlodScene {
+        let lights = room::split_by_room(all_lights, &mut rooms, |s| &mut s.lights);
+        for room in &mut rooms {
+            room.scene.sort_content();
+        }
+        let mut scene = KlodScene {
+            version: SCENE_VERSION,
+            metadata,
             game_timer_seconds: timer.time,
+            templates,
             objects,
-            klod_spawn_transform: klod_spawn.0.into(),
+            klod_spawn_transform,
             music_triggers,
             finish_zone: finish_zone.get_single().unwrap().into(),
             required_score: timer.required_score,
             lights,
-        }
+            lighting,
+            checkpoints,
+            kill_zones,
+            trigger_zones,
+            force_volumes,
+            respawn,
+            rules,
+            slurp_rules,
+            tuning,
+            includes,
+            rooms,
+        };
+        scene.sort_content();
+        scene
     }
 
-    pub(crate) fn load(
-        world: &mut World,
-        scene_path: impl AsRef<Path>,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        #[cfg(feature = "editor")]
-        {
-            Self::load_inner(world, &scene_path).or_else(|_| {
-                migration::migrate(&scene_path)?;
-                Self::load_inner(world, scene_path)
-            })
+    /// Give an ID to objects without one, and sort all level content, so that
+    /// saving an unchanged level produces the same file.
+    fn sort_content(&mut self) {
+        let position = |t: &SerdeTransform| t.translation.to_array().map(FloatOrd);
+        // Objects without an ID are sorted by name and position, so that they
+        // get the same IDs whatever the order they were read in.
+        self.objects.sort_by(|a, b| {
+            let a_key = (a.id.is_none(), a.id, &a.name, position(&a.transform));
+            a_key.cmp(&(b.id.is_none(), b.id, &b.name, position(&b.transform)))
+        });
+        let max_id = self.objects.iter().filter_map(|o| o.id).max();
+        let mut next_id = max_id.map_or(0, |id| id + 1);
+        for object in self.objects.iter_mut().filter(|o| o.id.is_none()) {
+            object.id = Some(next_id);
+            next_id += 1;
         }
-        #[cfg(not(feature = "editor"))]
-        {
-            Self::load_inner(world, scene_path)
+        self.music_triggers.sort_by(|a, b| {
+            (&a.name, position(&a.transform)).cmp(&(&b.name, position(&b.transform)))
+        });
+        self.lights.sort_by_key(SerdeLight::sort_key);
+        self.checkpoints.sort_by(|a, b| {
+            (&a.name, position(&a.transform)).cmp(&(&b.name, position(&b.transform)))
+        });
+        self.kill_zones.sort_by(|a, b| {
+            (&a.name, position(&a.transform)).cmp(&(&b.name, position(&b.transform)))
+        });
+        self.trigger_zones.sort_by(|a, b| {
+            (&a.name, position(&a.transform)).cmp(&(&b.name, position(&b.transform)))
+        });
+        self.force_volumes.sort_by(|a, b| {
+            (&a.name, position(&a.transform)).cmp(&(&b.name, position(&b.transform)))
+        });
+    }
+
+    pub(crate) fn lighting(&self) -> &LevelLighting {
+        &self.lighting
+    }
+
+    /// Load the level at `scene_path` in the assets directory, outside of
+    /// campaign mode, see [`crate::campaign::StartCampaignLevel`].
+    ///
+    /// The current level is replaced once the new one finished loading.
+    pub(crate) fn load(world: &mut World, scene_path: impl AsRef<Path>) {
+        let handle = world.resource::<AssetServer>().load(scene_path.as_ref());
+        world.insert_resource(LevelHandle(handle));
+        world.remove_resource::<PlayingCampaign>();
+    }
+
+    /// The result of the last [`KlodScene::load`], `None` while it is still loading.
+    #[cfg(feature = "editor")]
+    pub(crate) fn load_result(world: &World) -> Option<Result<(), Box<dyn Error + Send + Sync>>> {
+        let level = world.get_resource::<LevelHandle>()?;
+        let asset_server = world.resource::<AssetServer>();
+        match asset_server.get_load_state(&level.0) {
+            LoadState::Loaded => Some(Ok(())),
+            LoadState::Failed => {
+                let path = asset_server.get_handle_path(&level.0);
+                let path = path.map_or_else(String::new, |path| path.path().display().to_string());
+                Some(Err(format!("Failed to load {path}, see the log").into()))
+            }
+            LoadState::NotLoaded | LoadState::Loading | LoadState::Unloaded => None,
         }
     }
 
@@ -460,35 +809,6 @@ impl KlodScene {
         }
     }
 
-    pub(crate) fn load_data(
-        world: &mut World,
-        data: &[u8],
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let scene: KlodScene = ron::de::from_bytes(data)?;
-
-        Self::delete_current_scene(world);
-        let mut system_state = SystemState::<KlodSpawnQuery>::new(world);
-        let mut query = system_state.get_mut(world);
-        scene.spawn(&mut query);
-        system_state.apply(world);
-        Ok(())
-    }
-
-    fn load_inner(
-        world: &mut World,
-        scene_path: impl AsRef<Path>,
-    ) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let file = std::fs::File::open(scene_path)?;
-        let scene: KlodScene = ron::de::from_reader(file)?;
-
-        Self::delete_current_scene(world);
-        let mut system_state = SystemState::<KlodSpawnQuery>::new(world);
-        let mut query = system_state.get_mut(world);
-        scene.spawn(&mut query);
-        system_state.apply(world);
-        Ok(())
-    }
-
     pub(crate) fn reset(&self, world: &mut World) {
         Self::delete_current_scene(world);
 
@@ -498,23 +818,158 @@ impl KlodScene {
         system_state.apply(world);
     }
 
+    /// Save the current level to `scene_path`, and its rooms to their own level files.
     #[cfg(feature = "editor")]
     pub(crate) fn save(
         world: &mut World,
         scene_path: impl AsRef<Path>,
     ) -> Result<(), Box<dyn Error + Send + Sync>> {
         let scene = KlodScene::from_world(world);
-        let serialized = ron::ser::to_string_pretty(
-            &scene,
-            ron::ser::PrettyConfig::new()
+        let root = get_base_path();
+        let mut written = vec![scene_path.as_ref().to_owned()];
+        scene.write(scene_path)?;
+        for room in &scene.rooms {
+            let room_path = root.join(&room.path);
+            room.scene.write(&room_path)?;
+            written.push(room_path);
+        }
+        // Only loaded files are watched for changes.
+        let asset_server = world.resource::<AssetServer>();
+        let watched: Vec<HandleId> = written
+            .iter()
+            .filter_map(|path| path.strip_prefix(&root).ok())
+            .map(|path| AssetPath::from(path).into())
+            .filter(|id| asset_server.get_load_state(*id) != LoadState::NotLoaded)
+            .collect();
+        world.resource_mut::<SavedFiles>().0.extend(watched);
+        Ok(())
+    }
+
+    /// Write this level to `scene_path`, as a `.klodbin` file if it has this
+    /// extension, as a `.klodlvl` file otherwise.
+    #[cfg(feature = "editor")]
+    fn write(&self, scene_path: impl AsRef<Path>) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let scene_path = scene_path.as_ref();
+        let serialized = if scene_path.extension() == Some("klodbin".as_ref()) {
+            binary::to_bytes(self)?
+        } else {
+            let config = ron::ser::PrettyConfig::new()
                 .indentor(" ".to_owned())
-                .depth_limit(80),
-        )?;
+                .depth_limit(80);
+            ron::ser::to_string_pretty(self, config)?.into_bytes()
+        };
         std::fs::write(scene_path, serialized)?;
         Ok(())
     }
 }
 
+/// Read the level at `from` and write it to `to`, converting between the
+/// `.klodlvl` and `.klodbin` formats according to the file extensions.
+#[cfg(feature = "editor")]
+pub fn convert_level(
+    from: impl AsRef<Path>,
+    to: impl AsRef<Path>,
+) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let data = std::fs::read(from)?;
+    migration::from_bytes(&data)?.write(to)
+}
+
+#[derive(Default)]
+struct KlodSceneLoader;
+impl AssetLoader for KlodSceneLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let mut scene = migration::from_bytes(bytes).map_err(anyhow::Error::msg)?;
+            scene.rooms = room::load_rooms(&scene.includes, load_context).await?;
+            // Load the rooms as their own assets, so that they are watched for changes.
+            let rooms = scene.rooms.iter();
+            let dependencies = rooms
+                .map(|room| AssetPath::from(room.path.clone()))
+                .collect();
+            load_context.set_default_asset(LoadedAsset::new(scene).with_dependencies(dependencies));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["klodlvl", "klodbin"]
+    }
+}
+
+/// Load the level at the given path in the assets directory, see [`KlodScene::load`].
+pub(crate) struct LoadLevel(pub(crate) String);
+impl Command for LoadLevel {
+    fn write(self, world: &mut World) {
+        KlodScene::load(world, self.0);
+    }
+}
+
+struct RespawnScene(KlodScene);
+impl Command for RespawnScene {
+    fn write(self, world: &mut World) {
+        self.0.reset(world);
+    }
+}
+
+/// Spawn the level in [`LevelHandle`] when it is loaded or modified on disk.
+///
+/// When one of its room files is modified, the room is replaced in the level,
+/// which is then respawned as modified.
+fn respawn_loaded_scene(
+    mut cmds: Commands,
+    mut events: EventReader<AssetEvent<KlodScene>>,
+    mut scenes: ResMut<Assets<KlodScene>>,
+    mut saved: ResMut<SavedFiles>,
+    asset_server: Res<AssetServer>,
+    level: Option<Res<LevelHandle>>,
+) {
+    let level = match level {
+        Some(level) => level,
+        None => return,
+    };
+    // `Created` is not sent when loading again an already loaded level.
+    let mut respawn = level.is_changed();
+    let mut modified = Vec::new();
+    for event in events.iter() {
+        match event {
+            AssetEvent::Modified { handle } if saved.0.remove(&handle.id) => {}
+            AssetEvent::Modified { handle } if *handle != level.0 => {
+                modified.push(handle.clone_weak());
+            }
+            AssetEvent::Created { handle } | AssetEvent::Modified { handle } => {
+                respawn |= *handle == level.0;
+            }
+            AssetEvent::Removed { .. } => {}
+        }
+    }
+    for handle in &modified {
+        let (path, room_scene) = match (asset_server.get_handle_path(handle), scenes.get(handle)) {
+            (Some(path), Some(scene)) => (path, scene.clone()),
+            _ => continue,
+        };
+        let is_modified = |room: &Room| Path::new(&room.path) == path.path();
+        let rooms = scenes.get(&level.0).map(|scene| &scene.rooms);
+        if !rooms.map_or(false, |rooms| rooms.iter().any(is_modified)) {
+            continue;
+        }
+        // Modifying the level asset sends a `Modified` event, respawning it.
+        if let Some(level_scene) = scenes.get_mut(&level.0) {
+            for room in &mut level_scene.rooms {
+                if is_modified(room) {
+                    room.scene = KlodScene { rooms: Vec::new(), ..room_scene.clone() };
+                }
+            }
+        }
+    }
+    if let Some(scene) = scenes.get(&level.0).filter(|_| respawn) {
+        cmds.add(RespawnScene(scene.clone()));
+    }
+}
+
 #[derive(Component)]
 struct ComputeDefaultAabb;
 
@@ -559,26 +1014,103 @@ fn add_scene_aabb(
         visited.push((*entity, (aabb, *scale)));
     }
     for (entity, (aabb, scale)) in visited.into_iter() {
-        let extents = aabb.half_extents / scale;
-        let collider = SerdeCollider::Cuboid { half_extents: extents.into() };
+        let collider = SerdeCollider::Cuboid { half_extents: aabb.half_extents.into() };
+        let collider = collider / Vec3::from(scale);
         if aabb.min().min_element() != f32::MIN && aabb.max().max_element() != f32::MAX {
-            commands.entity(entity).insert_bundle((
-                Collider::from(collider.clone()),
-                mesh_assets.add(collider.into()),
-                aabb,
-            ));
+            let mut entity_cmds = commands.entity(entity);
+            entity_cmds.insert_bundle((mesh_assets.add(collider.clone().into()), aabb));
+            insert_collider(&mut entity_cmds, collider);
         }
         to_visit.remove(&entity);
     }
 }
 
+/// The mesh of a [`LevelCollider`], until it is loaded and its [`Collider`]
+/// inserted.
+#[derive(Component)]
+struct PendingMesh(Handle<Mesh>);
+
+/// Start loading the mesh of new [`SerdeCollider::TriMesh`] colliders.
+fn request_mesh_colliders(
+    mut cmds: Commands,
+    added: Query<(Entity, &LevelCollider), Added<LevelCollider>>,
+    assets: Res<AssetServer>,
+) {
+    for (entity, LevelCollider(collider)) in &added {
+        if let SerdeCollider::TriMesh { mesh, .. } = collider {
+            let mesh = assets.load(mesh.clone());
+            cmds.entity(entity).insert(PendingMesh(mesh));
+        }
+    }
+}
+
+fn load_mesh_colliders(
+    mut cmds: Commands,
+    pending: Query<(Entity, &LevelCollider, &PendingMesh)>,
+    meshes: Res<Assets<Mesh>>,
+) {
+    for (entity, LevelCollider(collider), PendingMesh(handle)) in &pending {
+        let (mesh, path, scale) = match (meshes.get(handle), collider) {
+            (Some(mesh), SerdeCollider::TriMesh { mesh: path, scale }) => (mesh, path, *scale),
+            _ => continue,
+        };
+        let mut entity = cmds.entity(entity);
+        entity.remove::<PendingMesh>();
+        match Collider::from_bevy_mesh(mesh, &ComputedColliderShape::TriMesh) {
+            Some(mut mesh_collider) => {
+                mesh_collider.set_scale(scale, 10);
+                entity.insert(mesh_collider);
+            }
+            None => error!("Can't make a collider of mesh {path:?}"),
+        }
+    }
+}
+
+/// The scale baked into the [`Collider`] of a [`LevelCollider`].
+#[cfg(feature = "editor")]
+#[derive(Component)]
+struct BakedScale(Vec3);
+
+/// Bake the new scale of objects scaled in the editor into their collider.
+#[cfg(feature = "editor")]
+fn rebake_scaled_colliders(
+    mut scaled: Query<
+        (&LevelCollider, &Transform, &mut Collider, &mut BakedScale),
+        Changed<Transform>,
+    >,
+) {
+    for (LevelCollider(collider), transform, mut physics_collider, mut baked) in &mut scaled {
+        let scale = transform.scale;
+        if baked.0 == scale {
+            continue;
+        }
+        baked.0 = scale;
+        if let Some(rebaked) = (collider.clone() * scale).to_collider() {
+            *physics_collider = rebaked;
+        }
+    }
+}
+
 fn fit_pickbox_to_collider(
-    mut colliders: Query<(&Collider, &Handle<Mesh>, &mut Aabb), Changed<Collider>>,
+    mut colliders: Query<
+        (
+            &Collider,
+            Option<&ColliderScale>,
+            &Transform,
+            &Handle<Mesh>,
+            &mut Aabb,
+        ),
+        Changed<Collider>,
+    >,
     mut meshes: ResMut<Assets<Mesh>>,
 ) {
-    for (collider, mesh, mut aabb) in &mut colliders {
+    for (collider, collider_scale, transform, mesh, mut aabb) in &mut colliders {
         if let Some(mesh) = meshes.get_mut(mesh) {
-            *mesh = SerdeCollider::from(collider).into();
+            // The transform scales the mesh, but not colliders with a baked scale.
+            *mesh = match collider_scale {
+                Some(ColliderScale::Absolute(_)) => baked_collider_mesh(collider, transform.scale),
+                _ => collider_mesh(collider),
+            };
             if let Some(new_aabb) = mesh.compute_aabb() {
                 *aabb = new_aabb;
             }
@@ -621,7 +1153,18 @@ impl BevyPlugin for Plugin {
         #[cfg(feature = "debug")]
         app.register_inspectable::<Scenery>();
 
-        app.add_system_to_stage(CoreStage::PostUpdate, add_scene_aabb)
+        app.add_asset::<KlodScene>()
+            .init_asset_loader::<KlodSceneLoader>()
+            .add_system_to_stage(CoreStage::PostUpdate, add_scene_aabb)
+            .init_resource::<LevelStreaming>()
+            .init_resource::<SavedFiles>()
+            .add_system(respawn_loaded_scene)
+            .add_system(streaming::stream_level)
+            .add_system(request_mesh_colliders.before(load_mesh_colliders))
+            .add_system(load_mesh_colliders)
             .add_system(fit_pickbox_to_collider);
+
+        #[cfg(feature = "editor")]
+        app.add_system(rebake_scaled_colliders.before(fit_pickbox_to_collider));
     }
 }
@@ -0,0 +1,153 @@
+//! The `.klodbin` level format, a compact binary encoding of [`KlodScene`].
+//!
+//! Much faster to load than `.klodlvl` files, but not meant to be edited by
+//! hand: use the `klod-convert` binary to generate it from a `.klodlvl` file.
+//!
+//! A `.klodbin` file is [`MAGIC`], the [`SCENE_VERSION`] it was written with
+//! as a little-endian `u32`, followed by the [`BinaryScene`] encoded with
+//! `bincode`. Only the current version can be read, since `.klodbin` files
+//! are always generated from the `.klodlvl` source.
+use std::error::Error;
+
+use serde::Deserialize;
+
+use super::{
+    room::Include, CheckpointData, FinishZone, ForceVolumeData, KillZoneData, KlodScene,
+    KlodTuning, LevelLighting, LevelMetadata, LevelRule, LevelSlurpRule, MusicTriggerData,
+    PhysicsObject, RespawnRules, SerdeLight, SerdeTransform, TriggerZoneData, SCENE_VERSION,
+};
+
+const MAGIC: &[u8; 8] = b"KLODBIN\0";
+const HEADER_LEN: usize = MAGIC.len() + 4;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Layout of a [`KlodScene`] in the `.klodbin` file.
+///
+/// Unlike [`super::template::KlodSceneFile`], all fields are always written,
+/// as `bincode` can't tell which fields are missing.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize)]
+struct BinaryScene {
+    metadata: LevelMetadata,
+    klod_spawn_transform: SerdeTransform,
+    finish_zone: FinishZone,
+    game_timer_seconds: f32,
+    objects: Vec<PhysicsObject>,
+    music_triggers: Vec<MusicTriggerData>,
+    required_score: f32,
+    lights: Vec<SerdeLight>,
+    lighting: LevelLighting,
+    checkpoints: Vec<CheckpointData>,
+    kill_zones: Vec<KillZoneData>,
+    trigger_zones: Vec<TriggerZoneData>,
+    force_volumes: Vec<ForceVolumeData>,
+    respawn: RespawnRules,
+    rules: Vec<LevelRule>,
+    slurp_rules: Vec<LevelSlurpRule>,
+    tuning: Option<KlodTuning>,
+    includes: Vec<Include>,
+}
+
+/// Whether `data` is the content of a `.klodbin` file.
+pub(super) fn is_binary(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+pub(super) fn from_bytes(data: &[u8]) -> Result<KlodScene> {
+    let header = data
+        .get(MAGIC.len()..HEADER_LEN)
+        .ok_or("Truncated .klodbin file")?;
+    // unwrap: `header` is exactly 4 bytes long
+    let version = u32::from_le_bytes(header.try_into().unwrap());
+    if version != SCENE_VERSION {
+        let msg = format!(
+            "Binary level version {version} is not the current version {SCENE_VERSION}, \
+            convert its .klodlvl file again"
+        );
+        return Err(msg.into());
+    }
+    let binary: BinaryScene = bincode::deserialize(&data[HEADER_LEN..])?;
+    Ok(KlodScene {
+        version,
+        metadata: binary.metadata,
+        klod_spawn_transform: binary.klod_spawn_transform,
+        finish_zone: binary.finish_zone,
+        game_timer_seconds: binary.game_timer_seconds,
+        // Objects of binary levels are stored with their templates applied.
+        templates: Default::default(),
+        objects: binary.objects,
+        music_triggers: binary.music_triggers,
+        required_score: binary.required_score,
+        lights: binary.lights,
+        lighting: binary.lighting,
+        checkpoints: binary.checkpoints,
+        kill_zones: binary.kill_zones,
+        trigger_zones: binary.trigger_zones,
+        force_volumes: binary.force_volumes,
+        respawn: binary.respawn,
+        rules: binary.rules,
+        slurp_rules: binary.slurp_rules,
+        tuning: binary.tuning,
+        includes: binary.includes,
+        rooms: Vec::new(),
+    })
+}
+
+#[cfg(feature = "editor")]
+pub(super) fn to_bytes(scene: &KlodScene) -> Result<Vec<u8>> {
+    let binary = BinaryScene {
+        metadata: scene.metadata.clone(),
+        klod_spawn_transform: scene.klod_spawn_transform,
+        finish_zone: scene.finish_zone.clone(),
+        game_timer_seconds: scene.game_timer_seconds,
+        objects: scene.objects.clone(),
+        music_triggers: scene.music_triggers.clone(),
+        required_score: scene.required_score,
+        lights: scene.lights.clone(),
+        lighting: scene.lighting.clone(),
+        checkpoints: scene.checkpoints.clone(),
+        kill_zones: scene.kill_zones.clone(),
+        trigger_zones: scene.trigger_zones.clone(),
+        force_volumes: scene.force_volumes.clone(),
+        respawn: scene.respawn.clone(),
+        rules: scene.rules.clone(),
+        slurp_rules: scene.slurp_rules.clone(),
+        tuning: scene.tuning.clone(),
+        includes: scene.includes.clone(),
+    };
+    let mut data = Vec::with_capacity(HEADER_LEN);
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&SCENE_VERSION.to_le_bytes());
+    bincode::serialize_into(&mut data, &binary)?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "editor")]
+    #[test]
+    fn round_trip() {
+        let level = include_bytes!("../../assets/default.klodlvl");
+        let scene = super::super::migration::from_bytes(level).unwrap();
+        let binary = to_bytes(&scene).unwrap();
+        assert!(is_binary(&binary));
+        let read = from_bytes(&binary).unwrap();
+        assert_eq!(read.objects.len(), scene.objects.len());
+        assert_eq!(to_bytes(&read).unwrap(), binary);
+    }
+
+    #[test]
+    fn rejects_other_versions() {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&(SCENE_VERSION - 1).to_le_bytes());
+        assert!(from_bytes(&data).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_files() {
+        assert!(from_bytes(MAGIC).is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Lights of a level, and its ambient light and sky colours.
+use bevy::{ecs::query::WorldQuery, prelude::*, utils::FloatOrd};
+use serde::Deserialize;
+
+use super::room::InRoom;
+use crate::{prefabs::SerdeTransform, LightSwitch};
+
+/// Entities with one of the light components.
+pub(crate) type AnyLight = Or<(With<PointLight>, With<SpotLight>, With<DirectionalLight>)>;
+
+/// The light-specific fields of a [`SerdeLight`].
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, Copy)]
+pub(super) enum LightKind {
+    Point {
+        intensity: f32,
+        range: f32,
+        radius: f32,
+    },
+    Spot {
+        intensity: f32,
+        range: f32,
+        radius: f32,
+        /// Angles in radians, see [`SpotLight`].
+        inner_angle: f32,
+        outer_angle: f32,
+    },
+    /// Shines from the `position` rotation, its translation is ignored.
+    Directional { illuminance: f32 },
+}
+
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone)]
+pub(super) struct SerdeLight {
+    pub(super) color: Color,
+    pub(super) position: SerdeTransform,
+    pub(super) shadows: bool,
+    pub(super) kind: LightKind,
+}
+impl SerdeLight {
+    /// Order of lights in level files: by position, then kind, rotation, color
+    /// and the other fields, so that saving the same lights gives the same file.
+    pub(super) fn sort_key(&self) -> impl Ord {
+        let (kind, fields) = match self.kind {
+            LightKind::Point { intensity, range, radius } => {
+                (0, [intensity, range, radius, 0.0, 0.0])
+            }
+            LightKind::Spot { intensity, range, radius, inner_angle, outer_angle } => {
+                (1, [intensity, range, radius, inner_angle, outer_angle])
+            }
+            LightKind::Directional { illuminance } => (2, [illuminance, 0.0, 0.0, 0.0, 0.0]),
+        };
+        let SerdeTransform { translation, rotation, .. } = self.position;
+        (
+            translation.to_array().map(FloatOrd),
+            kind,
+            rotation.to_array().map(FloatOrd),
+            self.color.as_rgba_f32().map(FloatOrd),
+            fields.map(FloatOrd),
+            self.shadows,
+        )
+    }
+    pub(super) fn spawn(&self, cmds: &mut Commands, is_visible: bool) -> Entity {
+        let transform = self.position.into();
+        let visibility = Visibility { is_visible };
+        let (color, shadows_enabled) = (self.color, self.shadows);
+        let entity = match self.kind {
+            LightKind::Point { intensity, range, radius } => cmds.spawn_bundle(PointLightBundle {
+                point_light: PointLight {
+                    color,
+                    intensity,
+                    range,
+                    radius,
+                    shadows_enabled,
+                    ..default()
+                },
+                transform,
+                visibility,
+                ..default()
+            }),
+            LightKind::Spot { intensity, range, radius, inner_angle, outer_angle } => cmds
+                .spawn_bundle(SpotLightBundle {
+                    spot_light: SpotLight {
+                        color,
+                        intensity,
+                        range,
+                        radius,
+                        shadows_enabled,
+                        inner_angle,
+                        outer_angle,
+                        ..default()
+                    },
+                    transform,
+                    visibility,
+                    ..default()
+                }),
+            LightKind::Directional { illuminance } => cmds.spawn_bundle(DirectionalLightBundle {
+                directional_light: DirectionalLight {
+                    color,
+                    illuminance,
+                    shadows_enabled,
+                    ..default()
+                },
+                transform,
+                visibility,
+                ..default()
+            }),
+        };
+        entity.id()
+    }
+}
+
+#[derive(WorldQuery)]
+pub(super) struct LightQuery {
+    point: Option<&'static PointLight>,
+    spot: Option<&'static SpotLight>,
+    directional: Option<&'static DirectionalLight>,
+    transform: &'static Transform,
+    pub(super) room: Option<&'static InRoom>,
+}
+impl LightQueryItem<'_> {
+    /// `None` if this entity has none of the light components.
+    pub(super) fn data(&self) -> Option<SerdeLight> {
+        let (color, shadows, kind) = match (self.point, self.spot, self.directional) {
+            (Some(light), ..) => {
+                let kind = LightKind::Point {
+                    intensity: light.intensity,
+                    range: light.range,
+                    radius: light.radius,
+                };
+                (light.color, light.shadows_enabled, kind)
+            }
+            (_, Some(light), _) => {
+                let kind = LightKind::Spot {
+                    intensity: light.intensity,
+                    range: light.range,
+                    radius: light.radius,
+                    inner_angle: light.inner_angle,
+                    outer_angle: light.outer_angle,
+                };
+                (light.color, light.shadows_enabled, kind)
+            }
+            (.., Some(light)) => {
+                let kind = LightKind::Directional { illuminance: light.illuminance };
+                (light.color, light.shadows_enabled, kind)
+            }
+            (None, None, None) => return None,
+        };
+        let position = (*self.transform).into();
+        Some(SerdeLight { color, position, shadows, kind })
+    }
+}
+
+/// Ambient light and background colour of a level.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct LevelLighting {
+    ambient_color: Color,
+    ambient_brightness: f32,
+    clear_color: Color,
+}
+impl Default for LevelLighting {
+    fn default() -> Self {
+        LevelLighting {
+            ambient_color: Color::WHITE,
+            ambient_brightness: 0.8,
+            clear_color: Color::rgb(0.293, 0.3828, 0.4023),
+        }
+    }
+}
+impl LevelLighting {
+    /// The ambient light, brighter when the lights are switched off.
+    pub(crate) fn ambient(&self, light_switch: &LightSwitch) -> AmbientLight {
+        let brightness = if light_switch.on {
+            self.ambient_brightness
+        } else {
+            self.ambient_brightness.max(1.0)
+        };
+        AmbientLight { color: self.ambient_color, brightness }
+    }
+    pub(super) fn clear_color(&self) -> ClearColor {
+        ClearColor(self.clear_color)
+    }
+}
@@ -0,0 +1,293 @@
+//! Find mistakes in `.klodlvl` files without running the game.
+//!
+//! Used by the `klod-lint` binary. Rooms included by the level are checked
+//! as part of it, placed where the game spawns them.
+use std::{error::Error, fmt, mem, path::Path};
+
+use bevy::{
+    asset::AssetPath,
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use bevy_rapier3d::{rapier::parry::bounding_volume::AABB, utils};
+
+use super::{
+    migration,
+    room::{self, Placed, Room},
+    KlodScene, ObjectType,
+};
+use crate::{
+    powers::Power,
+    prefabs::{SerdeCollider, SerdeTransform},
+    trigger::TriggerAction,
+};
+
+/// A mistake found in a level file.
+#[derive(Debug)]
+pub enum LintIssue {
+    /// The level file can't be read or parsed.
+    Unreadable { error: String },
+    /// A transform has a scale component that is zero or negative.
+    BadScale { name: String, scale: Vec3 },
+    /// The `asset_path` of an object or the level thumbnail doesn't exist in the assets directory.
+    MissingAsset { name: String, path: String },
+    /// The finish zone doesn't touch any object, so it probably is unreachable.
+    IsolatedFinishZone,
+    /// A scenery object can only be destroyed with a power no agglomerable provides.
+    UnobtainablePower { name: String, power: String },
+    /// Several objects share the same name.
+    DuplicateName { name: String, count: usize },
+    /// A trigger zone opens a door no scenery object is named after.
+    MissingDoor { name: String, door: String },
+    /// The mesh of a `TriMesh` collider can't be read from its glTF file.
+    BadMesh {
+        name: String,
+        path: String,
+        error: String,
+    },
+}
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::Unreadable { error } => write!(f, "could not load level: {error}"),
+            LintIssue::BadScale { name, scale } => {
+                write!(f, "{name}: scale {scale} has a zero or negative component")
+            }
+            LintIssue::MissingAsset { name, path } => {
+                write!(f, "{name}: asset file \"{path}\" does not exist")
+            }
+            LintIssue::IsolatedFinishZone => write!(f, "Finish zone: doesn't overlap any object"),
+            LintIssue::UnobtainablePower { name, power } => {
+                write!(
+                    f,
+                    "{name}: weak to {power}, but no agglomerable has this power"
+                )
+            }
+            LintIssue::DuplicateName { name, count } => {
+                write!(f, "{name}: {count} objects have this name")
+            }
+            LintIssue::MissingDoor { name, door } => {
+                write!(
+                    f,
+                    "{name}: opens door \"{door}\", but no scenery has this name"
+                )
+            }
+            LintIssue::BadMesh { name, path, error } => {
+                write!(f, "{name}: can't read collider mesh \"{path}\": {error}")
+            }
+        }
+    }
+}
+
+/// `None` if `collider` has no volume or is a mesh that can't be read.
+fn world_aabb(collider: &SerdeCollider, transform: SerdeTransform) -> Option<AABB> {
+    // Scaled like the colliders of spawned objects, see `PhysicsObject::spawn`.
+    let collider = (collider.clone() * transform.scale).to_collider()?;
+    let isometry = utils::transform_to_iso(&transform.into(), 1.0);
+    Some(collider.raw.compute_aabb(&isometry))
+}
+
+/// The bounding box of the glTF mesh primitive at `mesh`, such as
+/// `"crypt.glb#Mesh0/Primitive0"`, as a convex hull scaled by `scale`.
+///
+/// glTF files store the bounds of mesh positions, the buffers aren't read.
+fn mesh_bounds(root: &Path, mesh: &AssetPath, scale: Vec3) -> Result<SerdeCollider, String> {
+    let indices = mesh.label().and_then(|label| {
+        let (mesh, primitive) = label.strip_prefix("Mesh")?.split_once("/Primitive")?;
+        Some((mesh.parse().ok()?, primitive.parse().ok()?))
+    });
+    let (mesh_index, primitive_index): (usize, usize) =
+        indices.ok_or("the label is not of the form Mesh<N>/Primitive<N>")?;
+    let gltf = gltf::Gltf::open(root.join(mesh.path())).map_err(|err| err.to_string())?;
+    let primitive = gltf
+        .meshes()
+        .nth(mesh_index)
+        .and_then(|mesh| mesh.primitives().nth(primitive_index))
+        .ok_or("the file has no such mesh primitive")?;
+    let bounds = primitive.bounding_box();
+    let (min, max) = (Vec3::from(bounds.min), Vec3::from(bounds.max));
+    let corners = (0..8).map(|i| {
+        let pick = |bit, min: f32, max: f32| if i & bit == 0 { min } else { max };
+        Vec3::new(
+            pick(1, min.x, max.x),
+            pick(2, min.y, max.y),
+            pick(4, min.z, max.z),
+        ) * scale
+    });
+    Ok(SerdeCollider::ConvexHull { points: corners.collect() })
+}
+
+/// Replace `TriMesh` colliders by the bounds of their mesh, so that other
+/// checks don't treat them as points.
+fn resolve_meshes(scene: &mut KlodScene, problems: &mut Vec<LintIssue>) {
+    let root = super::get_base_path();
+    let finish_zone = ("Finish zone", &mut scene.finish_zone.collider);
+    let objects = scene
+        .objects
+        .iter_mut()
+        .map(|object| (object.name.as_str(), &mut object.collider));
+    for (name, collider) in objects.chain([finish_zone]) {
+        let (mesh, scale) = match collider {
+            SerdeCollider::TriMesh { mesh, scale } => (mesh, *scale),
+            _ => continue,
+        };
+        let (name, path) = (name.to_owned(), mesh.path().display().to_string());
+        if !root.join(mesh.path()).exists() {
+            problems.push(LintIssue::MissingAsset { name, path });
+            continue;
+        }
+        let path = format!("{path}#{}", mesh.label().unwrap_or_default());
+        match mesh_bounds(&root, mesh, scale) {
+            Ok(bounds) => *collider = bounds,
+            Err(error) => problems.push(LintIssue::BadMesh { name, path, error }),
+        }
+    }
+}
+
+fn bad_scales(scene: &KlodScene, problems: &mut Vec<LintIssue>) {
+    let mut check = |name: &str, transform: &SerdeTransform| {
+        let scale = transform.scale;
+        if scale.cmple(Vec3::ZERO).any() {
+            problems.push(LintIssue::BadScale { name: name.to_owned(), scale });
+        }
+    };
+    check("Klod spawn", &scene.klod_spawn_transform);
+    check("Finish zone", &scene.finish_zone.transform);
+    for object in &scene.objects {
+        check(&object.name, &object.transform);
+    }
+    for music in &scene.music_triggers {
+        check(&music.name, &music.transform);
+    }
+    for light in &scene.lights {
+        check("Light", &light.position);
+    }
+}
+
+fn missing_assets(scene: &KlodScene, problems: &mut Vec<LintIssue>) {
+    let root = super::get_base_path();
+    for object in &scene.objects {
+        let path = match &object.asset_path {
+            Some(asset_path) if asset_path.path() != Path::new("") => asset_path.path(),
+            _ => continue,
+        };
+        if !root.join(path).exists() {
+            let path = path.display().to_string();
+            problems.push(LintIssue::MissingAsset { name: object.name.clone(), path });
+        }
+    }
+    if let Some(path) = &scene.metadata.thumbnail {
+        if !root.join(path).exists() {
+            let (name, path) = ("Thumbnail".to_owned(), path.clone());
+            problems.push(LintIssue::MissingAsset { name, path });
+        }
+    }
+}
+
+fn isolated_finish_zone(scene: &KlodScene, problems: &mut Vec<LintIssue>) {
+    let finish = &scene.finish_zone;
+    let finish_aabb = match world_aabb(&finish.collider, finish.transform) {
+        Some(aabb) => aabb,
+        None => return,
+    };
+    let touches_object = scene
+        .objects
+        .iter()
+        .filter_map(|object| world_aabb(&object.collider, object.transform))
+        .any(|aabb| aabb.intersects(&finish_aabb));
+    if !touches_object {
+        problems.push(LintIssue::IsolatedFinishZone);
+    }
+}
+
+fn unobtainable_powers(scene: &KlodScene, problems: &mut Vec<LintIssue>) {
+    // The klod ball itself has the `None` power.
+    let mut provided = HashSet::from_iter([Power::None]);
+    for object in &scene.objects {
+        if let ObjectType::Agglomerable(agglo) = &object.object {
+            provided.insert(agglo.power);
+        }
+    }
+    for object in &scene.objects {
+        let scenery = match &object.object {
+            ObjectType::Scenery(scenery) => scenery,
+            ObjectType::Agglomerable(_) | ObjectType::Kinematic(_) | ObjectType::TimePickup(_) => {
+                continue
+            }
+        };
+        let missing = scenery.weakness.iter().filter(|p| !provided.contains(p));
+        for power in missing {
+            let name = object.name.clone();
+            problems.push(LintIssue::UnobtainablePower { name, power: power.to_string() });
+        }
+    }
+}
+
+fn duplicate_names(scene: &KlodScene, problems: &mut Vec<LintIssue>) {
+    let mut counts: HashMap<&str, usize> = HashMap::default();
+    for object in &scene.objects {
+        *counts.entry(&object.name).or_default() += 1;
+    }
+    let mut duplicates: Vec<_> = counts.into_iter().filter(|(_, count)| *count > 1).collect();
+    duplicates.sort_unstable();
+    for (name, count) in duplicates {
+        problems.push(LintIssue::DuplicateName { name: name.to_owned(), count });
+    }
+}
+
+fn missing_doors(scene: &KlodScene, problems: &mut Vec<LintIssue>) {
+    let scenery: HashSet<&str> = scene
+        .objects
+        .iter()
+        .filter(|object| matches!(object.object, ObjectType::Scenery(_)))
+        .map(|object| object.name.as_str())
+        .collect();
+    for zone in &scene.trigger_zones {
+        for action in &zone.actions {
+            match action {
+                TriggerAction::OpenDoor(door) if !scenery.contains(door.as_str()) => {
+                    let (name, door) = (zone.name.clone(), door.clone());
+                    problems.push(LintIssue::MissingDoor { name, door });
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// All the [`LintIssue`]s in the level file at `scene_path`.
+pub fn lint_level(scene_path: impl AsRef<Path>) -> Vec<LintIssue> {
+    lint_file(scene_path)
+        .unwrap_or_else(|error| vec![LintIssue::Unreadable { error: error.to_string() }])
+}
+
+/// Add to `items` the `room_items` of all `rooms`, at their place in the level.
+fn merge_rooms<'a, T: Placed + 'a>(
+    items: &mut Vec<T>,
+    rooms: &'a [Room],
+    room_items: impl Fn(&'a KlodScene) -> &'a Vec<T> + 'a,
+) {
+    let level_items = mem::take(items);
+    let all_items = room::with_rooms(level_items, rooms, room_items);
+    *items = all_items.map(|(_, item)| item).collect();
+}
+
+fn lint_file(scene_path: impl AsRef<Path>) -> Result<Vec<LintIssue>, Box<dyn Error + Send + Sync>> {
+    let data = std::fs::read(scene_path)?;
+    let mut scene = migration::from_bytes(&data)?;
+    // Objects of a room may be named in another, check the level as a whole.
+    let rooms = room::read_rooms(&scene.includes, &super::get_base_path())?;
+    merge_rooms(&mut scene.objects, &rooms, |s| &s.objects);
+    merge_rooms(&mut scene.music_triggers, &rooms, |s| &s.music_triggers);
+    merge_rooms(&mut scene.lights, &rooms, |s| &s.lights);
+    merge_rooms(&mut scene.trigger_zones, &rooms, |s| &s.trigger_zones);
+    let mut problems = Vec::new();
+    bad_scales(&scene, &mut problems);
+    missing_assets(&scene, &mut problems);
+    resolve_meshes(&mut scene, &mut problems);
+    isolated_finish_zone(&scene, &mut problems);
+    unobtainable_powers(&scene, &mut problems);
+    duplicate_names(&scene, &mut problems);
+    missing_doors(&scene, &mut problems);
+    Ok(problems)
+}
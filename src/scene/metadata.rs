@@ -0,0 +1,126 @@
+//! Title, author and description of levels, shown in the level select screen.
+use std::path::Path;
+
+use serde::Deserialize;
+
+/// Extension of the level files loaded by this build, see [`level_file`].
+///
+/// Release builds ship the faster to load binary levels, generated with `klod-convert`.
+#[cfg(feature = "debug")]
+const LEVEL_EXTENSION: &str = "klodlvl";
+#[cfg(not(feature = "debug"))]
+const LEVEL_EXTENSION: &str = "klodbin";
+
+/// Path of the file of `level` loaded by this build, `level` being relative
+/// to the assets directory, with any extension.
+///
+/// Release builds fall back to the `.klodlvl` file when the `.klodbin` file
+/// wasn't generated.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn level_file(level: impl AsRef<Path>) -> String {
+    let path = level.as_ref().with_extension(LEVEL_EXTENSION);
+    let path = if super::get_base_path().join(&path).exists() {
+        path
+    } else {
+        path.with_extension("klodlvl")
+    };
+    path.to_string_lossy().into_owned()
+}
+
+/// The web build can't check for files, `wasm_build.sh` generates all `.klodbin` files.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn level_file(level: impl AsRef<Path>) -> String {
+    let path = level.as_ref().with_extension(LEVEL_EXTENSION);
+    path.to_string_lossy().into_owned()
+}
+
+/// Description of a level, all fields are optional in level files.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(default)]
+pub(crate) struct LevelMetadata {
+    pub(crate) title: String,
+    pub(crate) author: String,
+    pub(crate) description: String,
+    /// Path of an image in the assets directory.
+    pub(crate) thumbnail: Option<String>,
+}
+
+/// A level file in the assets directory.
+pub(crate) struct LevelEntry {
+    /// Path relative to the assets directory, as accepted by [`super::KlodScene::load`].
+    pub(crate) path: String,
+    pub(crate) metadata: LevelMetadata,
+}
+impl LevelEntry {
+    /// The level title, or its file name if it has none.
+    pub(crate) fn title(&self) -> &str {
+        match self.metadata.title.as_str() {
+            "" => self
+                .path
+                .rsplit_once('.')
+                .map_or(self.path.as_str(), |(name, _)| name),
+            title => title,
+        }
+    }
+}
+
+/// Just the `metadata` field of a `.klodlvl` file, all other fields are ignored.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Deserialize)]
+struct MetadataHeader {
+    #[serde(default)]
+    metadata: LevelMetadata,
+}
+
+/// Read the metadata of a level file, without reading the whole level when possible.
+#[cfg(not(target_arch = "wasm32"))]
+fn read_metadata(data: &[u8]) -> Result<LevelMetadata, Box<dyn std::error::Error + Send + Sync>> {
+    if super::binary::is_binary(data) {
+        return Ok(super::binary::from_bytes(data)?.metadata);
+    }
+    let header: MetadataHeader = ron::de::from_bytes(data)?;
+    Ok(header.metadata)
+}
+
+/// All levels in the assets directory, sorted by file name.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn list_levels() -> Vec<LevelEntry> {
+    use bevy::log::error;
+
+    let entries = match std::fs::read_dir(super::get_base_path()) {
+        Ok(entries) => entries,
+        Err(err) => {
+            error!("Can't list the levels in the assets directory: {err}");
+            return Vec::new();
+        }
+    };
+    let mut levels: Vec<_> = entries
+        .filter_map(|entry| {
+            let path = entry.ok()?.path();
+            // Also lists the `.klodlvl` files without a `.klodbin` file in release builds.
+            if path.extension() != Some("klodlvl".as_ref()) {
+                return None;
+            }
+            let path = super::get_base_path().join(level_file(path.file_name()?));
+            let metadata = std::fs::read(&path)
+                .map_err(Into::into)
+                .and_then(|data| read_metadata(&data))
+                .unwrap_or_else(|err| {
+                    error!("Can't read the metadata of {path:?}: {err}");
+                    LevelMetadata::default()
+                });
+            let path = path.file_name()?.to_str()?.to_owned();
+            Some(LevelEntry { path, metadata })
+        })
+        .collect();
+    levels.sort_by(|a, b| a.path.cmp(&b.path));
+    levels
+}
+
+/// The web build can't list the assets directory, only the default level is available.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn list_levels() -> Vec<LevelEntry> {
+    let path = level_file("default");
+    vec![LevelEntry { path, metadata: LevelMetadata::default() }]
+}
@@ -1,8 +1,12 @@
-use std::{error::Error, path::Path};
+use std::error::Error;
 
-use bevy::prelude::Vec3;
+use bevy::prelude::{Color, Vec3};
 use serde::Deserialize;
 
+use super::{
+    light::{LightKind, SerdeLight},
+    template::KlodSceneFile,
+};
 use crate::prefabs::SerdeCollider;
 
 #[derive(Deserialize, Debug)]
@@ -11,10 +15,10 @@ struct KlodSceneV1 {
     objects: Vec<super::PhysicsObject>,
     music_triggers: Vec<super::MusicTriggerData>,
 }
-impl From<KlodSceneV1> for super::KlodScene {
+impl From<KlodSceneV1> for KlodSceneV2 {
     fn from(v1: KlodSceneV1) -> Self {
         let KlodSceneV1 { klod_spawn_transform, objects, music_triggers } = v1;
-        super::KlodScene {
+        KlodSceneV2 {
             klod_spawn_transform,
             objects,
             music_triggers,
@@ -23,8 +27,6 @@ impl From<KlodSceneV1> for super::KlodScene {
                 transform: Default::default(),
             },
             game_timer_seconds: 1.5 * 60.0,
-            required_score: 1000.0,
-            lights: Vec::new(),
         }
     }
 }
@@ -36,7 +38,7 @@ pub(crate) struct KlodSceneV2 {
     objects: Vec<super::PhysicsObject>,
     music_triggers: Vec<super::MusicTriggerData>,
 }
-impl From<KlodSceneV2> for super::KlodScene {
+impl From<KlodSceneV2> for KlodSceneV3 {
     fn from(v2: KlodSceneV2) -> Self {
         let KlodSceneV2 {
             klod_spawn_transform,
@@ -45,14 +47,13 @@ impl From<KlodSceneV2> for super::KlodScene {
             objects,
             music_triggers,
         } = v2;
-        super::KlodScene {
+        KlodSceneV3 {
             klod_spawn_transform,
             finish_zone,
             game_timer_seconds,
             objects,
             music_triggers,
             required_score: 1000.0,
-            lights: Vec::new(),
         }
     }
 }
@@ -76,38 +77,172 @@ impl From<KlodSceneV3> for super::KlodScene {
             required_score,
         } = v3;
         super::KlodScene {
+            version: super::SCENE_VERSION,
+            metadata: Default::default(),
             klod_spawn_transform,
             finish_zone,
             game_timer_seconds,
+            templates: Default::default(),
             objects,
             music_triggers,
             required_score,
             lights: Vec::new(),
+            lighting: Default::default(),
+            checkpoints: Vec::new(),
+            kill_zones: Vec::new(),
+            trigger_zones: Vec::new(),
+            force_volumes: Vec::new(),
+            respawn: Default::default(),
+            rules: Vec::new(),
+            slurp_rules: Vec::new(),
+            tuning: None,
+            includes: Vec::new(),
+            rooms: Vec::new(),
+        }
+    }
+}
+
+/// Lights of version 6 and older, all lights were point lights without shadows.
+#[derive(Deserialize, Debug)]
+struct SerdeLightV6 {
+    intensity: f32,
+    color: Color,
+    position: super::SerdeTransform,
+    radius: f32,
+}
+impl From<SerdeLightV6> for SerdeLight {
+    fn from(v6: SerdeLightV6) -> Self {
+        let SerdeLightV6 { intensity, color, position, radius } = v6;
+        // The range used to be derived from this specific intensity.
+        let range = if intensity == 55555.0 { 30.0 } else { 20.0 };
+        SerdeLight {
+            color,
+            position,
+            shadows: false,
+            kind: LightKind::Point { intensity, range, radius },
         }
     }
 }
+type KlodSceneV6 = KlodSceneFile<SerdeLightV6>;
+
+type Result<T> = std::result::Result<T, Box<dyn Error + Send + Sync>>;
+
+/// Just the `version` field of a `.klodlvl` file, all other fields are ignored.
+#[derive(Deserialize)]
+struct VersionHeader {
+    version: Option<u32>,
+}
+
+fn upgrade_v1(v1: KlodSceneV1) -> super::KlodScene {
+    upgrade_v2(v1.into())
+}
+fn upgrade_v2(v2: KlodSceneV2) -> super::KlodScene {
+    upgrade_v3(v2.into())
+}
+fn upgrade_v3(v3: KlodSceneV3) -> super::KlodScene {
+    v3.into()
+}
+fn upgrade_v6(v6: KlodSceneV6) -> Result<super::KlodScene> {
+    Ok(v6.map_lights(SerdeLight::from).try_into()?)
+}
+
+fn read<V: for<'a> Deserialize<'a>>(data: &[u8]) -> Result<V> {
+    Ok(ron::de::from_bytes(data)?)
+}
 
-fn try_load<V>(
-    scene_path: impl AsRef<Path>,
-) -> Result<super::KlodScene, Box<dyn Error + Send + Sync>>
-where
-    V: for<'a> Deserialize<'a> + Into<super::KlodScene>,
-{
-    let file = std::fs::File::open(&scene_path)?;
-    let scene: V = ron::de::from_reader(file)?;
-    Ok(scene.into())
+/// Read a `.klodlvl` file of any version, upgrading it to the current format,
+/// or a `.klodbin` file, see [`super::binary`].
+///
+/// Files saved before the `version` field existed are detected by trying
+/// each format from newest to oldest.
+pub(super) fn from_bytes(data: &[u8]) -> Result<super::KlodScene> {
+    if super::binary::is_binary(data) {
+        return super::binary::from_bytes(data);
+    }
+    let header: VersionHeader = read(data)?;
+    let mut scene = match header.version {
+        Some(1) => upgrade_v1(read(data)?),
+        Some(2) => upgrade_v2(read(data)?),
+        Some(3) => upgrade_v3(read(data)?),
+        // Versions 4 and 5 lack object templates and includes, which are optional.
+        Some(4 | 5 | 6) => upgrade_v6(read(data)?)?,
+        // Versions 7 to 16 lack the metadata, checkpoints, kill zones, respawn
+        // rules, kinematic objects, trigger zones, level rules, time pickups,
+        // force volumes, physics tuning and slurp rules, which are optional.
+        Some(7..=16 | super::SCENE_VERSION) => read(data)?,
+        Some(version) => {
+            let current = super::SCENE_VERSION;
+            return Err(format!("Unsupported level version {version}, latest is {current}").into());
+        }
+        None => read(data).or_else(|err| {
+            read(data)
+                .and_then(upgrade_v6)
+                .or_else(|_| read(data).map(upgrade_v3))
+                .or_else(|_| read(data).map(upgrade_v2))
+                .or_else(|_| read(data).map(upgrade_v1))
+                .map_err(|_| err)
+        })?,
+    };
+    scene.version = super::SCENE_VERSION;
+    scene.sort_content();
+    Ok(scene)
 }
 
-pub(super) fn migrate(scene_path: impl AsRef<Path>) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let new_scene_format = Err(())
-        .or_else(|_| try_load::<KlodSceneV1>(&scene_path))
-        .or_else(|_| try_load::<KlodSceneV2>(&scene_path))?;
-    let serialized = ron::ser::to_string_pretty(
-        &new_scene_format,
-        ron::ser::PrettyConfig::new()
-            .indentor(" ".to_owned())
-            .depth_limit(80),
-    )?;
-    std::fs::write(scene_path, serialized)?;
-    Ok(())
+#[cfg(test)]
+mod tests {
+    use super::{super::SCENE_VERSION, *};
+
+    const SPAWN: &str =
+        "(rotation: (0.0, 0.0, 0.0, 1.0), scale: (1.0, 1.0, 1.0), translation: (1.0, 2.0, 3.0))";
+
+    #[test]
+    fn upgrades_v1() {
+        let v1 =
+            format!("(version: 1, klod_spawn_transform: {SPAWN}, objects: [], music_triggers: [])");
+        let scene = from_bytes(v1.as_bytes()).unwrap();
+        assert_eq!(scene.version, SCENE_VERSION);
+        assert_eq!(
+            scene.klod_spawn_transform.translation,
+            Vec3::new(1.0, 2.0, 3.0)
+        );
+        assert_eq!(scene.game_timer_seconds, 90.0);
+        assert_eq!(scene.required_score, 1000.0);
+    }
+
+    #[test]
+    fn detects_unversioned_files() {
+        let v2 = format!(
+            "(klod_spawn_transform: {SPAWN}, finish_zone: (collider: Ball(radius: 2.0), \
+             transform: {SPAWN}), game_timer_seconds: 30.0, objects: [], music_triggers: [])"
+        );
+        let scene = from_bytes(v2.as_bytes()).unwrap();
+        assert_eq!(scene.version, SCENE_VERSION);
+        assert_eq!(scene.game_timer_seconds, 30.0);
+        assert_eq!(scene.required_score, 1000.0);
+    }
+
+    #[test]
+    fn rejects_newer_versions() {
+        let newer = format!("(version: {})", SCENE_VERSION + 1);
+        let err = from_bytes(newer.as_bytes()).unwrap_err();
+        assert!(err.to_string().starts_with("Unsupported level version"));
+    }
+
+    #[test]
+    fn upgrades_v6_lights() {
+        let v6 = format!(
+            "(version: 6, klod_spawn_transform: {SPAWN}, finish_zone: (collider: Ball(radius: 2.0), \
+             transform: {SPAWN}), game_timer_seconds: 30.0, objects: [], music_triggers: [], \
+             required_score: 10.0, lights: [(intensity: 55555.0, color: Rgba(red: 1.0, \
+             green: 1.0, blue: 1.0, alpha: 1.0), position: {SPAWN}, radius: 0.5)])"
+        );
+        let scene = from_bytes(v6.as_bytes()).unwrap();
+        let light = &scene.lights[0];
+        assert!(!light.shadows);
+        assert!(matches!(
+            light.kind,
+            LightKind::Point { intensity, range, radius }
+                if intensity == 55555.0 && range == 30.0 && radius == 0.5
+        ));
+    }
 }
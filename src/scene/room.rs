@@ -0,0 +1,233 @@
+//! Include other level files as rooms of a level.
+//!
+//! The content of included levels is spawned in the world, moved by the
+//! `offset` of the [`Include`]. Entities of a room
+//! are marked with [`InRoom`], so that saving the level writes them back to
+//! the file they came from. Room files are watched for changes like the level
+//! file, modifying one respawns the level.
+use std::{
+    collections::{HashSet, VecDeque},
+    path::Path,
+};
+
+use anyhow::anyhow;
+use bevy::{asset::LoadContext, prelude::*};
+use serde::Deserialize;
+
+use super::{migration, KlodScene, PhysicsObject, SerdeLight};
+use crate::prefabs::{
+    CheckpointData, ForceVolumeData, KillZoneData, MusicTriggerData, SerdeTransform,
+    TriggerZoneData,
+};
+
+/// Nested includes deeper than this are most likely an include cycle.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+/// A level file included in another level.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct Include {
+    /// Path of the level file, relative to the assets directory.
+    path: String,
+    offset: SerdeTransform,
+}
+
+/// A level file included in the current level, possibly through other includes.
+#[derive(Debug, Clone)]
+pub(crate) struct Room {
+    pub(super) path: String,
+    /// Transform of the room in the world, combining the offsets of all
+    /// the includes leading to this room.
+    pub(super) offset: Transform,
+    /// The content of the level file of the room, without its own rooms.
+    pub(super) scene: KlodScene,
+}
+impl Room {
+    /// This room without the content placed in the level: objects, music
+    /// triggers, lights, checkpoints, kill zones, trigger zones and force volumes.
+    pub(super) fn emptied(&self) -> Self {
+        let mut scene = self.scene.clone();
+        scene.objects.clear();
+        scene.music_triggers.clear();
+        scene.lights.clear();
+        scene.checkpoints.clear();
+        scene.kill_zones.clear();
+        scene.trigger_zones.clear();
+        scene.force_volumes.clear();
+        Room {
+            path: self.path.clone(),
+            offset: self.offset,
+            scene,
+        }
+    }
+    /// Convert `transform`, relative to the room, into a world transform.
+    pub(super) fn to_world(&self, transform: SerdeTransform) -> SerdeTransform {
+        self.offset.mul_transform(transform.into()).into()
+    }
+    /// Convert world `transform` into a transform relative to the room.
+    pub(super) fn to_room(&self, transform: SerdeTransform) -> SerdeTransform {
+        let relative =
+            self.offset.compute_affine().inverse() * Transform::from(transform).compute_affine();
+        let (scale, rotation, translation) = relative.to_scale_rotation_translation();
+        SerdeTransform { rotation, scale, translation }
+    }
+}
+
+/// Index in [`KlodScene`] rooms of the room this entity was loaded from.
+#[derive(Component, Clone, Copy)]
+pub(crate) struct InRoom(pub(crate) usize);
+
+/// Level content placed in the world, relative to its room when in a room.
+pub(super) trait Placed: Clone {
+    fn transform_mut(&mut self) -> &mut SerdeTransform;
+}
+macro_rules! impl_placed {
+    ($($placed:ty),* $(,)?) => {
+        $(impl Placed for $placed {
+            fn transform_mut(&mut self) -> &mut SerdeTransform {
+                &mut self.transform
+            }
+        })*
+    };
+}
+impl_placed!(
+    PhysicsObject,
+    MusicTriggerData,
+    CheckpointData,
+    KillZoneData,
+    TriggerZoneData,
+    ForceVolumeData,
+);
+impl Placed for SerdeLight {
+    fn transform_mut(&mut self) -> &mut SerdeTransform {
+        &mut self.position
+    }
+}
+
+/// `level_items` followed by the `room_items` of all `rooms`, moved in the
+/// world, with the room they are in.
+pub(super) fn with_rooms<'a, T: Placed + 'a>(
+    level_items: Vec<T>,
+    rooms: &'a [Room],
+    room_items: impl Fn(&'a KlodScene) -> &'a Vec<T> + 'a,
+) -> impl Iterator<Item = (Option<InRoom>, T)> + 'a {
+    let in_rooms = rooms.iter().enumerate().flat_map(move |(i, room)| {
+        room_items(&room.scene)
+            .iter()
+            .cloned()
+            .map(move |mut item| {
+                let transform = item.transform_mut();
+                *transform = room.to_world(*transform);
+                (Some(InRoom(i)), item)
+            })
+    });
+    level_items
+        .into_iter()
+        .map(|item| (None, item))
+        .chain(in_rooms)
+}
+
+/// Move the `items` of a room to the `room_items` of their room in `rooms`,
+/// relative to the room, returns the items of the level itself.
+pub(super) fn split_by_room<T: Placed>(
+    items: impl IntoIterator<Item = (Option<InRoom>, T)>,
+    rooms: &mut [Room],
+    room_items: impl Fn(&mut KlodScene) -> &mut Vec<T>,
+) -> Vec<T> {
+    let mut level_items = Vec::new();
+    for (room, mut item) in items {
+        match room.and_then(|InRoom(i)| rooms.get_mut(i)) {
+            Some(room) => {
+                let transform = item.transform_mut();
+                *transform = room.to_room(*transform);
+                room_items(&mut room.scene).push(item);
+            }
+            None => level_items.push(item),
+        }
+    }
+    level_items
+}
+
+/// An include to read, see [`RoomReader`].
+struct PendingRoom {
+    include: Include,
+    parent_offset: Transform,
+    depth: usize,
+}
+
+/// Reads the level files of includes and the files they include in turn,
+/// see [`load_rooms`] and [`read_rooms`].
+struct RoomReader {
+    to_load: VecDeque<PendingRoom>,
+    loaded: HashSet<String>,
+    rooms: Vec<Room>,
+}
+impl RoomReader {
+    fn new(includes: &[Include]) -> Self {
+        let pending = |include: &Include| PendingRoom {
+            include: include.clone(),
+            parent_offset: Transform::identity(),
+            depth: 1,
+        };
+        RoomReader {
+            to_load: includes.iter().map(pending).collect(),
+            loaded: HashSet::new(),
+            rooms: Vec::new(),
+        }
+    }
+    /// The next include to read, `None` once all rooms are read.
+    fn next_room(&mut self) -> Result<Option<PendingRoom>, anyhow::Error> {
+        let pending = match self.to_load.pop_front() {
+            Some(pending) => pending,
+            None => return Ok(None),
+        };
+        let path = &pending.include.path;
+        if pending.depth > MAX_INCLUDE_DEPTH {
+            return Err(anyhow!("{path}: too many nested includes"));
+        }
+        // Saving the level would write the content of each copy to the same file.
+        if !self.loaded.insert(path.clone()) {
+            return Err(anyhow!("{path}: included more than once"));
+        }
+        Ok(Some(pending))
+    }
+    /// Add the room of `pending`, which level file contains `bytes`.
+    fn read(&mut self, pending: PendingRoom, bytes: &[u8]) -> Result<(), anyhow::Error> {
+        let PendingRoom { include, parent_offset, depth } = pending;
+        let scene = migration::from_bytes(bytes).map_err(anyhow::Error::msg)?;
+        let offset = parent_offset.mul_transform(include.offset.into());
+        let nested = scene.includes.iter().map(|nested| PendingRoom {
+            include: nested.clone(),
+            parent_offset: offset,
+            depth: depth + 1,
+        });
+        self.to_load.extend(nested);
+        self.rooms.push(Room { path: include.path, offset, scene });
+        Ok(())
+    }
+}
+
+/// Read all level files in `includes` and the files they include in turn.
+pub(super) async fn load_rooms(
+    includes: &[Include],
+    load_context: &LoadContext<'_>,
+) -> Result<Vec<Room>, anyhow::Error> {
+    let mut reader = RoomReader::new(includes);
+    while let Some(pending) = reader.next_room()? {
+        let bytes = load_context.read_asset_bytes(&pending.include.path).await?;
+        reader.read(pending, &bytes)?;
+    }
+    Ok(reader.rooms)
+}
+
+/// Like [`load_rooms`], but reading the files directly from the `root`
+/// assets directory, outside of the asset server.
+pub(super) fn read_rooms(includes: &[Include], root: &Path) -> Result<Vec<Room>, anyhow::Error> {
+    let mut reader = RoomReader::new(includes);
+    while let Some(pending) = reader.next_room()? {
+        let path = &pending.include.path;
+        let bytes = std::fs::read(root.join(path)).map_err(|err| anyhow!("{path}: {err}"))?;
+        reader.read(pending, &bytes)?;
+    }
+    Ok(reader.rooms)
+}
@@ -0,0 +1,88 @@
+//! Spawn the glTF scenes of objects only when they are near the klod.
+//!
+//! The level is split in cubic cells of [`LevelStreaming::cell_size`]. The
+//! scenes of objects further than `view_distance` cells away from the cell of
+//! the klod are despawned. Objects keep their collider, so that far away
+//! physics, powers and scoring behave the same. Moving objects, such as
+//! kinematic ones, are checked again whenever they move.
+use bevy::{
+    prelude::*,
+    scene::{SceneInstance, SceneSpawner},
+};
+
+use crate::ball::{Klod, KlodSpawnTransform};
+
+/// Settings of level streaming, see [`self`].
+pub(crate) struct LevelStreaming {
+    /// When disabled, all objects are spawned with their scene.
+    pub(crate) enabled: bool,
+    pub(crate) cell_size: f32,
+    /// How many cells away from the klod's cell objects are still visible.
+    pub(crate) view_distance: i32,
+}
+impl Default for LevelStreaming {
+    fn default() -> Self {
+        LevelStreaming {
+            // Level designers need to see the whole level.
+            enabled: !cfg!(feature = "editor"),
+            cell_size: 60.0,
+            view_distance: 2,
+        }
+    }
+}
+
+/// The scene of an object, only spawned when it is near the klod.
+#[derive(Component)]
+pub(super) struct Streamed(pub(super) Handle<Scene>);
+
+fn cell_of(position: Vec3, cell_size: f32) -> IVec3 {
+    (position / cell_size).floor().as_ivec3()
+}
+
+/// Spawn and despawn the scenes of [`Streamed`] objects when the klod
+/// changes cell, or when they move.
+pub(super) fn stream_level(
+    mut cmds: Commands,
+    mut scene_spawner: ResMut<SceneSpawner>,
+    mut last_cell: Local<Option<IVec3>>,
+    settings: Res<LevelStreaming>,
+    klod: Query<&GlobalTransform, With<Klod>>,
+    spawn_point: Res<KlodSpawnTransform>,
+    objects: Query<(
+        Entity,
+        &Streamed,
+        &GlobalTransform,
+        ChangeTrackers<GlobalTransform>,
+        Option<&Handle<Scene>>,
+        Option<&SceneInstance>,
+    )>,
+) {
+    let center = klod
+        .get_single()
+        .map_or(spawn_point.0.translation, GlobalTransform::translation);
+    let center_cell = cell_of(center, settings.cell_size);
+    let check_all = *last_cell != Some(center_cell) || settings.is_changed();
+    *last_cell = Some(center_cell);
+    for (entity, Streamed(scene), transform, moved, spawned, instance) in &objects {
+        // Also true for objects spawned since the last check.
+        if !check_all && !moved.is_changed() {
+            continue;
+        }
+        let offset = cell_of(transform.translation(), settings.cell_size) - center_cell;
+        let near = !settings.enabled || offset.abs().max_element() <= settings.view_distance;
+        match (near, spawned) {
+            (true, None) => {
+                cmds.entity(entity).insert(scene.clone());
+            }
+            (false, Some(_)) => {
+                if let Some(instance) = instance {
+                    scene_spawner.despawn_instance(**instance);
+                }
+                cmds.entity(entity)
+                    .remove::<Handle<Scene>>()
+                    .remove::<SceneInstance>();
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,478 @@
+//! Object templates, to avoid repeating the same physics properties for
+//! every object in `.klodlvl` files.
+//!
+//! [`KlodSceneFile`] is how a [`KlodScene`] is laid out in the level file,
+//! objects may refer to a named [`ObjectTemplate`] and only set the fields
+//! that differ from it. Saving a level keeps its templates, and only adds
+//! templates for properties shared by objects matching none of them.
+use std::collections::BTreeMap;
+
+use bevy::asset::AssetPath;
+use serde::Deserialize;
+
+use super::{
+    room::Include, FinishZone, KlodScene, LevelLighting, LevelMetadata, ObjectType, PhysicsObject,
+    SerdeLight,
+};
+use crate::{
+    audio::ImpactSound,
+    ball::{slurp::LevelSlurpRule, tuning::KlodTuning},
+    checkpoint::RespawnRules,
+    prefabs::{
+        CheckpointData, ForceVolumeData, KillZoneData, MusicTriggerData, SerdeCollider,
+        SerdeTransform, TriggerZoneData,
+    },
+    rules::LevelRule,
+};
+
+/// Fields of [`PhysicsObject`]s shared by several objects, unset fields
+/// must be set by the objects using the template.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(default)]
+pub(crate) struct ObjectTemplate {
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    collider: Option<SerdeCollider>,
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    friction: Option<f32>,
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    restitution: Option<f32>,
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    sounds: Option<Vec<ImpactSound>>,
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    object: Option<ObjectType>,
+}
+#[cfg(feature = "editor")]
+impl ObjectTemplate {
+    fn of(object: &PhysicsObject) -> Self {
+        ObjectTemplate {
+            collider: Some(object.collider.clone()),
+            friction: Some(object.friction),
+            restitution: Some(object.restitution),
+            sounds: Some(object.sounds.clone()),
+            object: Some(object.object.clone()),
+        }
+    }
+    /// The fields of `object` that differ from this template.
+    fn overrides(&self, object: &PhysicsObject) -> Self {
+        macro_rules! field {
+            ($field:ident) => {
+                Some(object.$field.clone()).filter(|value| self.$field.as_ref() != Some(value))
+            };
+        }
+        ObjectTemplate {
+            collider: field!(collider),
+            friction: field!(friction),
+            restitution: field!(restitution),
+            sounds: field!(sounds),
+            object: field!(object),
+        }
+    }
+}
+
+/// A [`PhysicsObject`] as written in the level file.
+///
+/// Fields set here take precedence over the ones of `template`.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone)]
+pub(crate) struct TemplatedObject {
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    id: Option<u32>,
+    name: String,
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    template: Option<String>,
+    asset_path: Option<AssetPath<'static>>,
+    transform: SerdeTransform,
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    collider: Option<SerdeCollider>,
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    friction: Option<f32>,
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    restitution: Option<f32>,
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    sounds: Option<Vec<ImpactSound>>,
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    object: Option<ObjectType>,
+}
+impl TemplatedObject {
+    fn resolve(
+        self,
+        templates: &BTreeMap<String, ObjectTemplate>,
+    ) -> Result<PhysicsObject, String> {
+        let empty = ObjectTemplate::default();
+        let name = self.name;
+        let template = match &self.template {
+            Some(template) => templates
+                .get(template)
+                .ok_or_else(|| format!("{name}: there is no template named \"{template}\""))?,
+            None => &empty,
+        };
+        let missing = |field| format!("{name}: {field} is neither set nor in a template");
+        macro_rules! field {
+            ($field:ident) => {
+                self.$field
+                    .or_else(|| template.$field.clone())
+                    .ok_or_else(|| missing(stringify!($field)))?
+            };
+        }
+        Ok(PhysicsObject {
+            collider: field!(collider),
+            friction: field!(friction),
+            restitution: field!(restitution),
+            sounds: field!(sounds),
+            object: field!(object),
+            id: self.id,
+            template: self.template,
+            name,
+            asset_path: self.asset_path,
+            transform: self.transform,
+        })
+    }
+}
+
+/// Layout of a [`KlodScene`] in the `.klodlvl` file.
+///
+/// `L` is the type of lights, it differs in files of older versions.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize)]
+pub(crate) struct KlodSceneFile<L = SerdeLight> {
+    /// Missing in files saved before versioning, see [`super::migration::from_bytes`].
+    #[serde(default)]
+    version: u32,
+    /// Missing in files of version 7 and older.
+    #[serde(default)]
+    metadata: LevelMetadata,
+    klod_spawn_transform: SerdeTransform,
+    finish_zone: FinishZone,
+    game_timer_seconds: f32,
+    #[serde(default)]
+    templates: BTreeMap<String, ObjectTemplate>,
+    objects: Vec<TemplatedObject>,
+    music_triggers: Vec<MusicTriggerData>,
+    required_score: f32,
+    lights: Vec<L>,
+    /// Missing in files of version 6 and older.
+    #[serde(default)]
+    lighting: LevelLighting,
+    /// Missing in files of version 8 and older.
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Vec::is_empty"))]
+    checkpoints: Vec<CheckpointData>,
+    /// Missing in files of version 9 and older.
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Vec::is_empty"))]
+    kill_zones: Vec<KillZoneData>,
+    /// Missing in files of version 11 and older.
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Vec::is_empty"))]
+    trigger_zones: Vec<TriggerZoneData>,
+    /// Missing in files of version 14 and older.
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Vec::is_empty"))]
+    force_volumes: Vec<ForceVolumeData>,
+    #[serde(default)]
+    respawn: RespawnRules,
+    /// Missing in files of version 12 and older.
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Vec::is_empty"))]
+    rules: Vec<LevelRule>,
+    /// Missing in files of version 16 and older.
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Vec::is_empty"))]
+    slurp_rules: Vec<LevelSlurpRule>,
+    /// Missing in files of version 15 and older.
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Option::is_none"))]
+    tuning: Option<KlodTuning>,
+    #[serde(default)]
+    #[cfg_attr(feature = "editor", serde(skip_serializing_if = "Vec::is_empty"))]
+    includes: Vec<Include>,
+}
+impl<L> KlodSceneFile<L> {
+    /// Convert the lights of this file, to upgrade files with an older light format.
+    pub(super) fn map_lights<M>(self, f: impl FnMut(L) -> M) -> KlodSceneFile<M> {
+        KlodSceneFile {
+            version: self.version,
+            metadata: self.metadata,
+            klod_spawn_transform: self.klod_spawn_transform,
+            finish_zone: self.finish_zone,
+            game_timer_seconds: self.game_timer_seconds,
+            templates: self.templates,
+            objects: self.objects,
+            music_triggers: self.music_triggers,
+            required_score: self.required_score,
+            lights: self.lights.into_iter().map(f).collect(),
+            lighting: self.lighting,
+            checkpoints: self.checkpoints,
+            kill_zones: self.kill_zones,
+            trigger_zones: self.trigger_zones,
+            force_volumes: self.force_volumes,
+            respawn: self.respawn,
+            rules: self.rules,
+            slurp_rules: self.slurp_rules,
+            tuning: self.tuning,
+            includes: self.includes,
+        }
+    }
+}
+impl TryFrom<KlodSceneFile> for KlodScene {
+    type Error = String;
+
+    fn try_from(file: KlodSceneFile) -> Result<Self, Self::Error> {
+        let templates = &file.templates;
+        let objects = file.objects.into_iter().map(|o| o.resolve(templates));
+        let objects = objects.collect::<Result<_, _>>()?;
+        Ok(KlodScene {
+            version: file.version,
+            metadata: file.metadata,
+            klod_spawn_transform: file.klod_spawn_transform,
+            finish_zone: file.finish_zone,
+            game_timer_seconds: file.game_timer_seconds,
+            templates: file.templates,
+            objects,
+            music_triggers: file.music_triggers,
+            required_score: file.required_score,
+            lights: file.lights,
+            lighting: file.lighting,
+            checkpoints: file.checkpoints,
+            kill_zones: file.kill_zones,
+            trigger_zones: file.trigger_zones,
+            force_volumes: file.force_volumes,
+            respawn: file.respawn,
+            rules: file.rules,
+            slurp_rules: file.slurp_rules,
+            tuning: file.tuning,
+            includes: file.includes,
+            rooms: Vec::new(),
+        })
+    }
+}
+
+/// Name of a new template for an object named `object_name`, `"Bone.020"` gives `"Bone"`.
+#[cfg(feature = "editor")]
+fn template_name(object_name: &str, templates: &BTreeMap<String, ObjectTemplate>) -> String {
+    let prefix = object_name.trim_end_matches(|c: char| c.is_numeric() || c == '.' || c == ' ');
+    let prefix = if prefix.is_empty() { "Object" } else { prefix };
+    let mut name = prefix.to_owned();
+    let mut suffix = 1;
+    while templates.contains_key(&name) {
+        suffix += 1;
+        name = format!("{prefix} {suffix}");
+    }
+    name
+}
+
+/// The name of the template in `templates` `object` was loaded with, or else
+/// of a template it matches, with the fields of `object` overriding it.
+#[cfg(feature = "editor")]
+fn existing_template(
+    object: &PhysicsObject,
+    templates: &BTreeMap<String, ObjectTemplate>,
+) -> Option<(String, ObjectTemplate)> {
+    let loaded = object
+        .template
+        .as_ref()
+        .and_then(|name| templates.get_key_value(name));
+    let matching = || {
+        let no_overrides = ObjectTemplate::default();
+        templates
+            .iter()
+            .find(|(_, template)| template.overrides(object) == no_overrides)
+    };
+    let (name, template) = loaded.or_else(matching)?;
+    Some((name.clone(), template.overrides(object)))
+}
+
+/// Move the properties shared by several objects into templates.
+///
+/// Objects keep the template of `templates` they were loaded with, new
+/// templates are only created for the objects matching none of `templates`.
+#[cfg(feature = "editor")]
+fn fold_templates(
+    mut templates: BTreeMap<String, ObjectTemplate>,
+    objects: Vec<PhysicsObject>,
+) -> (BTreeMap<String, ObjectTemplate>, Vec<TemplatedObject>) {
+    let mut shared: Vec<(ObjectTemplate, usize)> = Vec::new();
+    let new_patterns = objects
+        .iter()
+        .filter(|object| existing_template(object, &templates).is_none());
+    for object in new_patterns {
+        let template = ObjectTemplate::of(object);
+        match shared.iter_mut().find(|(t, _)| *t == template) {
+            Some((_, count)) => *count += 1,
+            None => shared.push((template, 1)),
+        }
+    }
+    let mut names: Vec<Option<String>> = vec![None; shared.len()];
+    let templated = objects.into_iter().map(|object| {
+        let (template, overrides) = match existing_template(&object, &templates) {
+            Some((name, overrides)) => (Some(name), overrides),
+            None => {
+                let template = ObjectTemplate::of(&object);
+                // unwrap: objects without an existing template were added to
+                // `shared` in the previous loop, `templates` only grew since.
+                let index = shared.iter().position(|(t, _)| *t == template).unwrap();
+                if shared[index].1 > 1 {
+                    let name = names[index].get_or_insert_with(|| {
+                        let name = template_name(&object.name, &templates);
+                        templates.insert(name.clone(), template);
+                        name
+                    });
+                    (Some(name.clone()), ObjectTemplate::default())
+                } else {
+                    (None, template)
+                }
+            }
+        };
+        TemplatedObject {
+            id: object.id,
+            name: object.name,
+            template,
+            asset_path: object.asset_path,
+            transform: object.transform,
+            collider: overrides.collider,
+            friction: overrides.friction,
+            restitution: overrides.restitution,
+            sounds: overrides.sounds,
+            object: overrides.object,
+        }
+    });
+    let templated = templated.collect();
+    (templates, templated)
+}
+
+#[cfg(feature = "editor")]
+impl From<KlodScene> for KlodSceneFile {
+    fn from(scene: KlodScene) -> Self {
+        let (templates, objects) = fold_templates(scene.templates, scene.objects);
+        KlodSceneFile {
+            version: scene.version,
+            metadata: scene.metadata,
+            klod_spawn_transform: scene.klod_spawn_transform,
+            finish_zone: scene.finish_zone,
+            game_timer_seconds: scene.game_timer_seconds,
+            templates,
+            objects,
+            music_triggers: scene.music_triggers,
+            required_score: scene.required_score,
+            lights: scene.lights,
+            lighting: scene.lighting,
+            checkpoints: scene.checkpoints,
+            kill_zones: scene.kill_zones,
+            trigger_zones: scene.trigger_zones,
+            force_volumes: scene.force_volumes,
+            respawn: scene.respawn,
+            rules: scene.rules,
+            slurp_rules: scene.slurp_rules,
+            tuning: scene.tuning,
+            includes: scene.includes,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::prefabs::Scenery;
+
+    #[cfg(feature = "editor")]
+    fn object(name: &str, friction: f32) -> PhysicsObject {
+        PhysicsObject {
+            id: None,
+            template: None,
+            name: name.to_owned(),
+            asset_path: None,
+            transform: SerdeTransform::default(),
+            collider: SerdeCollider::Ball { radius: 1.0 },
+            friction,
+            restitution: 0.0,
+            sounds: Vec::new(),
+            object: ObjectType::Scenery(Scenery { weakness: Vec::new() }),
+        }
+    }
+
+    #[test]
+    fn resolve_prefers_object_fields() {
+        let template = ObjectTemplate {
+            collider: Some(SerdeCollider::Ball { radius: 1.0 }),
+            friction: Some(0.5),
+            restitution: Some(0.0),
+            sounds: Some(Vec::new()),
+            object: None,
+        };
+        let templates = BTreeMap::from([("Bone".to_owned(), template)]);
+        let templated = TemplatedObject {
+            id: None,
+            name: "Bone.001".to_owned(),
+            template: Some("Bone".to_owned()),
+            asset_path: None,
+            transform: SerdeTransform::default(),
+            collider: None,
+            friction: Some(0.9),
+            restitution: None,
+            sounds: None,
+            object: Some(ObjectType::Scenery(Scenery { weakness: Vec::new() })),
+        };
+        let resolved = templated.resolve(&templates).unwrap();
+        assert_eq!(resolved.friction, 0.9);
+        assert_eq!(resolved.template.as_deref(), Some("Bone"));
+    }
+
+    #[test]
+    fn resolve_rejects_unknown_templates() {
+        let templated = TemplatedObject {
+            id: None,
+            name: "Bone.001".to_owned(),
+            template: Some("Missing".to_owned()),
+            asset_path: None,
+            transform: SerdeTransform::default(),
+            collider: None,
+            friction: None,
+            restitution: None,
+            sounds: None,
+            object: None,
+        };
+        assert!(templated.resolve(&BTreeMap::new()).is_err());
+    }
+
+    #[cfg(feature = "editor")]
+    #[test]
+    fn fold_shares_new_templates() {
+        let objects = vec![
+            object("Bone.001", 0.5),
+            object("Bone.002", 0.5),
+            object("Rock", 0.1),
+        ];
+        let (templates, folded) = fold_templates(BTreeMap::new(), objects);
+        assert_eq!(templates.len(), 1);
+        assert!(templates.contains_key("Bone"));
+        assert_eq!(folded[0].template.as_deref(), Some("Bone"));
+        assert_eq!(folded[1].template.as_deref(), Some("Bone"));
+        assert_eq!(folded[0].friction, None);
+        assert_eq!(folded[2].template, None);
+        assert_eq!(folded[2].friction, Some(0.1));
+    }
+
+    #[cfg(feature = "editor")]
+    #[test]
+    fn fold_keeps_loaded_templates() {
+        let templates = BTreeMap::from([("Bone".to_owned(), ObjectTemplate::of(&object("", 0.5)))]);
+        let mut moved = object("Bone.001", 0.7);
+        moved.template = Some("Bone".to_owned());
+        let objects = vec![moved, object("Bone.002", 0.5)];
+        let (templates, folded) = fold_templates(templates, objects);
+        assert_eq!(templates.len(), 1);
+        assert_eq!(folded[0].template.as_deref(), Some("Bone"));
+        assert_eq!(folded[0].friction, Some(0.7));
+        assert_eq!(folded[0].collider, None);
+        assert_eq!(folded[1].template.as_deref(), Some("Bone"));
+        assert_eq!(folded[1].friction, None);
+    }
+}
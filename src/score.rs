@@ -9,27 +9,44 @@ use bevy_ui_navigation::prelude::{Focusable, NavEvent, NavEventReaderExt};
 use crate::{
     audio::{AudioAssets, AudioRequest, AudioRequestSystem},
     ball::{anim::DestroyKlodEvent, BallSystems, Klod, KlodBall},
+    campaign::{Campaign, CampaignHandle, PlayingCampaign, StartCampaignLevel},
     cleanup_marked,
     state::GameState,
     system_helper::EasySystemSetCtor,
     ui::{self, MenuCursor},
+    EndReason, GameOver,
 };
 
-struct Score {
+pub(crate) struct Score {
     bone_mass: f32,
     time_remaining: f32,
     required_mana: f32,
+    end_reason: Option<EndReason>,
 }
 impl Score {
+    pub(crate) fn new(timer: &GameData, klod: &Klod) -> Self {
+        Score {
+            bone_mass: klod.weight(),
+            time_remaining: timer.remaining(),
+            required_mana: timer.required_score,
+            end_reason: timer.end_reason,
+        }
+    }
     fn mana(&self) -> f32 {
         self.bone_mass * self.time_remaining
     }
-    fn won(&self) -> bool {
-        self.mana() > self.required_mana
+    pub(crate) fn won(&self) -> bool {
+        match self.end_reason {
+            Some(EndReason::Victory) => true,
+            Some(EndReason::Loss | EndReason::KillZone) => false,
+            None => self.mana() > self.required_mana,
+        }
     }
 
     fn hint(&self) -> &'static str {
-        if self.time_remaining <= 0.0 {
+        if self.end_reason == Some(EndReason::KillZone) {
+            "Fell off the map"
+        } else if self.time_remaining <= 0.0 {
             "Ran out of time"
         } else if !self.won() {
             "Not enough mana generated"
@@ -52,6 +69,8 @@ pub(crate) struct GameData {
     main_timer: Timer,
     pub(crate) time: f32,
     pub(crate) required_score: f32,
+    /// Set when a [`GameOver`] ended the game.
+    end_reason: Option<EndReason>,
 }
 impl GameData {
     pub(crate) fn new(time: f32, required_score: f32) -> Self {
@@ -59,10 +78,21 @@ impl GameData {
             time,
             main_timer: Timer::from_seconds(time, false),
             required_score,
+            end_reason: None,
         }
     }
     fn remaining(&self) -> f32 {
-        self.time - self.main_timer.elapsed_secs()
+        self.main_timer.duration().as_secs_f32() - self.main_timer.elapsed_secs()
+    }
+    /// Remove `seconds` from the remaining time.
+    pub(crate) fn penalize(&mut self, seconds: f32) {
+        self.add_time(-seconds);
+    }
+    /// Add `seconds` to the remaining time, remove time if negative.
+    pub(crate) fn add_time(&mut self, seconds: f32) {
+        let duration = self.main_timer.duration().as_secs_f32() + seconds;
+        self.main_timer
+            .set_duration(Duration::from_secs_f32(duration.max(0.0)));
     }
 }
 
@@ -71,6 +101,7 @@ pub(crate) struct FinishLine;
 
 fn init_timer(mut timer: ResMut<GameData>) {
     timer.main_timer = Timer::from_seconds(timer.time, false);
+    timer.end_reason = None;
 }
 
 /// This system controls ticking the timer within the countdown resource and
@@ -80,29 +111,27 @@ fn countdown(
     mut timer: ResMut<GameData>,
     mut destroy: EventWriter<DestroyKlodEvent>,
     mut state: ResMut<State<GameState>>,
-    mut held_down: Local<f32>,
-    gp_buttons: Res<Input<GamepadButton>>,
-    keys: Res<Input<KeyCode>>,
 ) {
     timer.main_timer.tick(time.delta());
     screen_print!("Time remaining: {:.0}", timer.remaining());
-    let gp_button = |button_type| GamepadButton { gamepad: Gamepad { id: 0 }, button_type };
-    let gp_start = gp_button(GamepadButtonType::Start);
-    let reset = keys.pressed(KeyCode::R) || gp_buttons.pressed(gp_start);
-    if reset {
-        *held_down += time.delta_seconds();
-    } else {
-        *held_down = 0.0;
-    }
-    if *held_down >= 1.0 {
-        timer.main_timer.set_elapsed(Duration::from_secs(10_000));
-    }
     if timer.main_timer.finished() {
         destroy.send(DestroyKlodEvent);
         state.set(GameState::TimeUp).unwrap();
     }
 }
 
+fn handle_game_over(
+    mut events: EventReader<GameOver>,
+    mut timer: ResMut<GameData>,
+    mut state: ResMut<State<GameState>>,
+) {
+    if let Some(GameOver(reason)) = events.iter().next() {
+        screen_print!("Game over: {reason:?}");
+        timer.end_reason = Some(*reason);
+        state.set(GameState::GameComplete).unwrap();
+    }
+}
+
 fn handle_finish(
     mut state: ResMut<State<GameState>>,
     finish_lines: Query<Entity, With<FinishLine>>,
@@ -131,25 +160,29 @@ struct ScoreboardUi;
 enum ScoreboardElem {
     MainMenu,
     Retry,
+    /// Play the campaign level at this index.
+    NextLevel(usize),
 }
 fn setup_scoreboard(
     timer: Res<GameData>,
     klod: Query<&Klod>,
     mut cmds: Commands,
     ui_assets: Res<ui::Assets>,
+    campaign: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+    playing: Option<Res<PlayingCampaign>>,
 ) {
     use FlexDirection as FD;
     use ScoreboardElem::*;
 
-    let bone_mass = match klod.get_single() {
-        Ok(klod) => klod.weight(),
+    let score = match klod.get_single() {
+        Ok(klod) => Score::new(&timer, klod),
         Err(_) => return,
     };
-    let score = Score {
-        bone_mass,
-        time_remaining: timer.remaining(),
-        required_mana: timer.required_score,
-    };
+    let campaign_len = campaign.levels(&campaigns).len();
+    let next_level = playing
+        .map(|playing| playing.index + 1)
+        .filter(|next| score.won() && *next < campaign_len);
 
     let text_bundle = |content: &str, color: Color, font_size: f32| {
         let style = TextStyle {
@@ -200,6 +233,9 @@ fn setup_scoreboard(
                 node[text(&score.time_label()); name("Time left")],
                 node[text(&score.mana_label()); name("Mana")]
             ),
+            if (next_level.is_some()) {
+                node[text("Next level"); focusable, name("Next level"), NextLevel(next_level.unwrap_or_default())]
+            },
             node[text("Retry"); focusable, name("Retry"), Retry],
             node[text("Main menu"); focusable, name("Mainmenu"), MainMenu]
         )
@@ -207,6 +243,7 @@ fn setup_scoreboard(
 }
 
 fn activate_scoreboard(
+    mut cmds: Commands,
     mut events: EventReader<NavEvent>,
     mut state: ResMut<State<GameState>>,
     elems: Query<&ScoreboardElem>,
@@ -215,6 +252,10 @@ fn activate_scoreboard(
         match activated {
             ScoreboardElem::MainMenu => state.set(GameState::MainMenu).unwrap(),
             ScoreboardElem::Retry => state.set(GameState::Playing).unwrap(),
+            ScoreboardElem::NextLevel(next) => {
+                cmds.add(StartCampaignLevel(*next));
+                state.set(GameState::Playing).unwrap();
+            }
         }
     }
 }
@@ -235,7 +276,8 @@ impl BevyPlugin for Plugin {
             .add_system_set(
                 SystemSet::on_update(GameState::Playing)
                     .with_system(countdown.before(BallSystems::DestroyKlod).before(times_up))
-                    .with_system(handle_finish),
+                    .with_system(handle_finish)
+                    .with_system(handle_game_over.after(handle_finish)),
             )
             .add_system_set(GameState::TimeUp.on_update(times_up.before(tada)))
             .add_system_set(GameState::GameComplete.on_enter(setup_scoreboard))
@@ -246,3 +288,30 @@ impl BevyPlugin for Plugin {
         ;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(bone_mass: f32, end_reason: Option<EndReason>) -> Score {
+        Score {
+            bone_mass,
+            time_remaining: 10.0,
+            required_mana: 100.0,
+            end_reason,
+        }
+    }
+
+    #[test]
+    fn won_with_enough_mana() {
+        assert!(score(20.0, None).won());
+        assert!(!score(5.0, None).won());
+    }
+
+    #[test]
+    fn end_reason_decides_won() {
+        assert!(score(0.0, Some(EndReason::Victory)).won());
+        assert!(!score(20.0, Some(EndReason::Loss)).won());
+        assert!(!score(20.0, Some(EndReason::KillZone)).won());
+    }
+}
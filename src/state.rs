@@ -2,6 +2,8 @@
 #[derive(Clone, Copy, Hash, PartialEq, Eq, Debug)]
 pub enum GameState {
     MainMenu,
+    /// Choice of the level to play, reached from the main menu
+    LevelSelect,
     Pause,
     #[cfg(feature = "editor")]
     Editor,
@@ -0,0 +1,184 @@
+//! Trigger zones, sensor volumes running a list of [`TriggerAction`]s when
+//! the klod goes through them.
+//!
+//! Unlike [`MusicTrigger`] and [`FinishLine`](crate::score::FinishLine),
+//! what a trigger zone does is entirely defined in the level file.
+use bevy::prelude::{Plugin as BevyPlugin, *};
+use bevy_debug_text_overlay::screen_print;
+use bevy_rapier3d::prelude::RapierContext;
+use serde::Deserialize;
+
+use crate::{
+    audio::{AudioAssets, AudioRequest, AudioRequestSystem, ImpactSound},
+    ball::KlodBall,
+    cleanup_marked,
+    game_audio::MusicTrigger,
+    prefabs::Scenery,
+    rules::LevelEvent,
+    score::GameData,
+    state::GameState,
+    system_helper::EasySystemSetCtor,
+    ui,
+};
+
+/// How long hints stay on screen, in seconds.
+const HINT_SECONDS: f32 = 5.0;
+
+/// When a [`TriggerZone`] runs its actions.
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TriggerWhen {
+    /// Each time the klod enters the zone.
+    Enter,
+    /// Each time the klod leaves the zone.
+    Exit,
+    /// The first time the klod enters the zone.
+    Once,
+}
+
+#[cfg_attr(feature = "editor", derive(serde::Serialize))]
+#[derive(Deserialize, Debug, Clone, PartialEq)]
+pub(crate) enum TriggerAction {
+    /// Play a new music track.
+    Music(MusicTrigger),
+    /// Show a text at the top of the screen for a few seconds.
+    Hint(String),
+    /// Add seconds to the timer, remove them if negative.
+    AddTime(f32),
+    /// Open the scenery object of the given name, removing it from the level.
+    OpenDoor(String),
+    /// Play a sound effect.
+    PlayEffect(ImpactSound),
+}
+
+/// A sensor volume running `actions` when the klod goes through it.
+#[derive(Component, Debug, Clone)]
+pub(crate) struct TriggerZone {
+    pub(crate) when: TriggerWhen,
+    pub(crate) actions: Vec<TriggerAction>,
+    inside: bool,
+    spent: bool,
+}
+impl TriggerZone {
+    pub(crate) fn new(when: TriggerWhen, actions: Vec<TriggerAction>) -> Self {
+        TriggerZone { when, actions, inside: false, spent: false }
+    }
+    /// Update whether the klod is `inside`, `true` if the actions should run.
+    fn update(&mut self, inside: bool) -> bool {
+        let entered = inside && !self.inside;
+        let exited = !inside && self.inside;
+        self.inside = inside;
+        let fire = match self.when {
+            TriggerWhen::Enter => entered,
+            TriggerWhen::Exit => exited,
+            TriggerWhen::Once => entered && !self.spent,
+        };
+        self.spent |= fire;
+        fire
+    }
+}
+
+/// Run a [`TriggerAction`].
+pub(crate) struct RunAction(pub(crate) TriggerAction);
+
+#[derive(Component)]
+struct HintText(Timer);
+
+fn detect_triggers(
+    mut zones: Query<(Entity, &mut TriggerZone, &Name)>,
+    mut actions: EventWriter<RunAction>,
+    mut level_events: EventWriter<LevelEvent>,
+    klods_query: Query<Entity, With<KlodBall>>,
+    rapier_context: Res<RapierContext>,
+    mut klods: Local<Vec<Entity>>,
+) {
+    klods.extend(&klods_query);
+    for (zone, mut trigger, name) in &mut zones {
+        let not_zone = |e1, e2| (e1 == zone).then(|| e2).unwrap_or(e1);
+        let inside = rapier_context
+            .intersections_with(zone)
+            .any(|(e1, e2, colliding)| colliding && klods.contains(&not_zone(e1, e2)));
+        if trigger.update(inside) {
+            screen_print!(sec: 3.0, col: Color::LIME_GREEN, "trigger zone: {:?}", trigger.actions);
+            actions.send_batch(trigger.actions.iter().cloned().map(RunAction));
+            level_events.send(LevelEvent::ZoneTriggered(name.to_string()));
+        }
+    }
+    klods.clear();
+}
+
+fn run_actions(
+    mut cmds: Commands,
+    mut events: EventReader<RunAction>,
+    mut audio_requests: EventWriter<AudioRequest>,
+    mut timer: ResMut<GameData>,
+    doors: Query<(Entity, &Name), With<Scenery>>,
+    hints: Query<Entity, With<HintText>>,
+    audio: Res<AudioAssets>,
+    ui_assets: Res<ui::Assets>,
+) {
+    for RunAction(action) in events.iter() {
+        match action {
+            TriggerAction::Music(MusicTrigger { intro: Some(intro), track }) => {
+                audio_requests.send(AudioRequest::QueueNewTrack(audio.track(*intro)));
+                audio_requests.send(AudioRequest::QueueMusic(audio.track(*track)));
+            }
+            TriggerAction::Music(MusicTrigger { intro: None, track }) => {
+                audio_requests.send(AudioRequest::QueueNewTrack(audio.track(*track)));
+            }
+            TriggerAction::Hint(text) => {
+                for hint in &hints {
+                    cmds.entity(hint).despawn_recursive();
+                }
+                let mut hint = ui_assets.large_text(text);
+                hint.style.position_type = PositionType::Absolute;
+                hint.style.position.top = Val::Px(40.0);
+                hint.style.align_self = AlignSelf::Center;
+                cmds.spawn_bundle(hint).insert_bundle((
+                    Name::new("Hint"),
+                    HintText(Timer::from_seconds(HINT_SECONDS, false)),
+                ));
+            }
+            TriggerAction::AddTime(seconds) => timer.add_time(*seconds),
+            TriggerAction::OpenDoor(door) => {
+                let mut opened = false;
+                for (entity, _) in doors.iter().filter(|(_, name)| name.as_str() == door) {
+                    cmds.entity(entity).despawn_recursive();
+                    opened = true;
+                }
+                if !opened {
+                    warn!("Trigger zone tried to open a missing door: {door}");
+                }
+            }
+            TriggerAction::PlayEffect(sound) => {
+                audio_requests.send(AudioRequest::PlayEffect(audio.impact(*sound), 1.0));
+            }
+        }
+    }
+}
+
+fn hide_hints(mut cmds: Commands, time: Res<Time>, mut hints: Query<(Entity, &mut HintText)>) {
+    for (entity, mut hint) in &mut hints {
+        if hint.0.tick(time.delta()).finished() {
+            cmds.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+pub(crate) struct Plugin;
+impl BevyPlugin for Plugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RunAction>()
+            .add_system_set(
+                GameState::Playing
+                    .on_update(detect_triggers)
+                    .with_system(
+                        run_actions
+                            .after(detect_triggers)
+                            .before(AudioRequestSystem),
+                    )
+                    .with_system(hide_hints),
+            )
+            .add_system_set(GameState::Playing.on_exit(cleanup_marked::<HintText>));
+    }
+}
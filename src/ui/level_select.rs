@@ -0,0 +1,201 @@
+//! Choice of the level to play, between all the levels of the assets directory.
+use super::common::{MenuCursor, UiAssets};
+use bevy::prelude::{Plugin as BevyPlugin, *};
+use bevy_ui_build_macros::{build_ui, rect, size, style, unit};
+use bevy_ui_navigation::prelude::*;
+
+use crate::{
+    audio::{AudioAssets, AudioRequest},
+    campaign::{Campaign, CampaignHandle, CampaignProgress, StartCampaignLevel},
+    cleanup_marked,
+    scene::{list_levels, LevelEntry, LoadLevel},
+    state::GameState,
+};
+
+#[derive(Clone, Component)]
+struct LevelSelectRoot;
+
+#[derive(Component, Clone, PartialEq)]
+enum LevelSelectElem {
+    /// Index of an unlocked level of the campaign.
+    CampaignLevel(usize),
+    /// Path of the level in the assets directory.
+    Level(String),
+    Back,
+}
+
+fn activate_level_select(
+    mut cmds: Commands,
+    mut events: EventReader<NavEvent>,
+    mut audio_requests: EventWriter<AudioRequest>,
+    mut game_state: ResMut<State<GameState>>,
+    audio: Res<AudioAssets>,
+    elems: Query<&LevelSelectElem>,
+) {
+    for activated in events.nav_iter().activated_in_query(&elems) {
+        audio_requests.send(AudioRequest::PlayEffect(audio.ui_click(), 0.05));
+        match activated {
+            LevelSelectElem::CampaignLevel(index) => {
+                cmds.add(StartCampaignLevel(*index));
+                game_state.set(GameState::Playing).unwrap();
+            }
+            LevelSelectElem::Level(path) => {
+                cmds.add(LoadLevel(path.clone()));
+                game_state.set(GameState::Playing).unwrap();
+            }
+            LevelSelectElem::Back => game_state.set(GameState::MainMenu).unwrap(),
+        }
+    }
+}
+
+/// Spawns an entry for `level`, with its thumbnail if it has one.
+///
+/// Only entries with an `elem` can be chosen.
+fn spawn_entry(
+    cmds: &mut Commands,
+    level: &LevelEntry,
+    elem: Option<LevelSelectElem>,
+    ui_assets: &UiAssets,
+    assets: &AssetServer,
+) -> Entity {
+    use FlexDirection as FD;
+
+    let text_bundle = |content: &str, font_size: f32| ui_assets.text_bundle(content, font_size);
+    let metadata = &level.metadata;
+    let title = match &elem {
+        Some(_) => level.title().to_owned(),
+        None => format!("{} (locked)", level.title()),
+    };
+    let node = NodeBundle {
+        color: Color::NONE.into(),
+        style: style! {
+            display: Display::Flex,
+            flex_direction: FD::ColumnReverse,
+            align_items: AlignItems::FlexStart,
+        },
+        ..Default::default()
+    };
+    let entry = build_ui! {
+        #[cmd(cmds)]
+        node {
+            flex_direction: FD::Row,
+            align_items: AlignItems::Center,
+            margin: rect!(10 px)
+        }[; Name::new(level.path.clone())](
+            node[; Name::new("Level description")](
+                node[ui_assets.large_text(&title);],
+                if (!metadata.author.is_empty()) {
+                    node[text_bundle(&format!("by {}", metadata.author), 25.0);]
+                },
+                if (!metadata.description.is_empty()) {
+                    node[text_bundle(&metadata.description, 25.0);]
+                },
+            )
+        )
+    }
+    .id();
+    if let Some(elem) = elem {
+        cmds.entity(entry)
+            .insert_bundle((Focusable::default(), elem));
+    }
+    if let Some(thumbnail) = &metadata.thumbnail {
+        let thumbnail = cmds
+            .spawn_bundle(ImageBundle {
+                image: assets.load(thumbnail.as_str()).into(),
+                style: style! { size: size!(160 px, 90 px), margin: rect!(10 px), },
+                ..Default::default()
+            })
+            .insert(Name::new("Level thumbnail"))
+            .id();
+        cmds.entity(entry).insert_children(0, &[thumbnail]);
+    }
+    entry
+}
+
+/// Spawns the UI tree
+fn setup_level_select(
+    mut cmds: Commands,
+    ui_assets: Res<UiAssets>,
+    assets: Res<AssetServer>,
+    campaign: Res<CampaignHandle>,
+    campaigns: Res<Assets<Campaign>>,
+    progress: Res<CampaignProgress>,
+) {
+    use FlexDirection as FD;
+    use LevelSelectElem::*;
+
+    let node = NodeBundle {
+        color: Color::NONE.into(),
+        style: style! {
+            display: Display::Flex,
+            flex_direction: FD::ColumnReverse,
+            align_items: AlignItems::Center,
+        },
+        ..Default::default()
+    };
+    let levels = list_levels();
+    if levels.is_empty() {
+        warn!("There are no levels in the assets directory");
+    }
+    let campaign_entries: Vec<_> = campaign
+        .levels(&campaigns)
+        .iter()
+        .enumerate()
+        .map(|(index, campaign_level)| {
+            let path = campaign_level.path();
+            let level = match levels.iter().find(|level| level.path == path) {
+                Some(level) => LevelEntry { path, metadata: level.metadata.clone() },
+                None => LevelEntry { path, metadata: default() },
+            };
+            let elem = progress.is_unlocked(index).then(|| CampaignLevel(index));
+            spawn_entry(&mut cmds, &level, elem, &ui_assets, &assets)
+        })
+        .collect();
+    let entries: Vec<_> = levels
+        .iter()
+        .map(|level| {
+            let elem = Some(Level(level.path.clone()));
+            spawn_entry(&mut cmds, level, elem, &ui_assets, &assets)
+        })
+        .collect();
+    let cursor = MenuCursor::spawn_ui_element(&mut cmds);
+
+    let mut list_style = node.style.clone();
+    list_style.align_items = AlignItems::FlexStart;
+    let mut list = |name: &str, entries: &[Entity]| {
+        cmds.spawn_bundle(NodeBundle { style: list_style.clone(), ..node.clone() })
+            .insert(Name::new(name.to_owned()))
+            .push_children(entries)
+            .id()
+    };
+    let campaign_list = list("Campaign levels", &campaign_entries);
+    let free_play_list = list("Free play levels", &entries);
+    build_ui! {
+        #[cmd(cmds)]
+        node{
+            min_size: size!(100 pct, 100 pct),
+            flex_direction: FD::ColumnReverse,
+            justify_content: JustifyContent::Center
+        }[; Name::new("Level select root node"), LevelSelectRoot](
+            id(cursor),
+            node[ui_assets.large_text("Campaign"); Name::new("Campaign title")],
+            id(campaign_list),
+            node[ui_assets.large_text("Free play"); Name::new("Free play title")],
+            id(free_play_list),
+            node[ui_assets.large_text("Back"); Focusable::default(), Name::new("Back"), Back]
+        )
+    };
+}
+
+pub struct Plugin(pub GameState);
+impl BevyPlugin for Plugin {
+    fn build(&self, app: &mut App) {
+        use crate::system_helper::EasySystemSetCtor;
+        app.add_system_set(self.0.on_enter(setup_level_select))
+            .add_system_set(self.0.on_exit(cleanup_marked::<LevelSelectRoot>))
+            .add_system_set(
+                self.0
+                    .on_update(activate_level_select.after(NavRequestSystem)),
+            );
+    }
+}
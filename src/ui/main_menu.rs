@@ -9,6 +9,7 @@ use crate::audio::{AudioAssets, IntroTrack, MusicTrack};
 use crate::{
     audio::{AudioRequest, AudioRequestSystem, SoundChannel},
     cleanup_marked,
+    scene::{AnyLight, CurrentScene},
     state::GameState,
 };
 use crate::{LightSwitch, UsesGamepad};
@@ -151,9 +152,10 @@ fn activate_menu(
     mut credit_overlay: Query<&mut Style, With<CreditOverlay>>,
     mut rules_overlay: Query<&mut Style, (Without<CreditOverlay>, With<RulesOverlay>)>,
     mut uses_gamepad: ResMut<UsesGamepad>,
-    mut lights: Query<&mut Visibility, With<PointLight>>,
+    mut lights: Query<&mut Visibility, AnyLight>,
     mut light_switch: ResMut<LightSwitch>,
     mut ambient_light: ResMut<AmbientLight>,
+    current: Option<Res<CurrentScene>>,
     audio: Res<AudioAssets>,
     elems: Query<&MainMenuElem>,
 ) {
@@ -164,7 +166,7 @@ fn activate_menu(
             MainMenuElem::Exit => exit.send(AppExit),
             MainMenuElem::Start => {
                 screen_print!("Player pressed the start button");
-                game_state.set(GameState::Playing).unwrap();
+                game_state.set(GameState::LevelSelect).unwrap();
             }
             MainMenuElem::LockMouse => {
                 let window = windows.get_primary_mut().expect(window_msg);
@@ -205,8 +207,9 @@ fn activate_menu(
                     vis.is_visible = !vis.is_visible;
                 }
                 light_switch.on = !light_switch.on;
-                let ambient_brightness = if light_switch.on { 0.8 } else { 1.0 };
-                ambient_light.brightness = ambient_brightness;
+                if let Some(current) = &current {
+                    *ambient_light = current.0.lighting().ambient(&light_switch);
+                }
             }
         }
     }
@@ -357,7 +360,8 @@ fn setup_main_menu(mut cmds: Commands, menu_assets: Res<MenuAssets>, ui_assets:
                     node[text_bundle("it has a 3 seconds cooldown.", 25.0);],
                     node[text_bundle("Reset", 30.0);],
                     node[text_bundle("If you get stuck or fall off the map, hold down R for 1 second", 25.0);],
-                    node[text_bundle("to insta-lose. Use start on controller.", 25.0);],
+                    node[text_bundle("to go back to the last checkpoint, at the cost of some time.", 25.0);],
+                    node[text_bundle("Use start on controller.", 25.0);],
                     node[text_bundle("Controls", 30.0);],
                     node[text_bundle("WASD/arrow keys/gamepad analog sticks", 25.0);],
                 )
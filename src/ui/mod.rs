@@ -1,5 +1,6 @@
 //! Menu and gameover screen ui.
 mod common;
+mod level_select;
 mod main_menu;
 
 pub use common::MenuCursor;
@@ -13,6 +14,7 @@ pub struct Plugin;
 impl BevyPlugin for Plugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(common::Plugin)
-            .add_plugin(main_menu::Plugin(GameState::MainMenu));
+            .add_plugin(main_menu::Plugin(GameState::MainMenu))
+            .add_plugin(level_select::Plugin(GameState::LevelSelect));
     }
 }